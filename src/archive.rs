@@ -0,0 +1,508 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::errors::common::CommonError;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Builds pushed as a single archive, read in place instead of being extracted to disk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    // None for a build directory, any other file must be an archive we can read
+    pub fn detect(path: &Path) -> Result<Option<ArchiveKind>, CommonError> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".zip") {
+            Ok(Some(ArchiveKind::Zip))
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Some(ArchiveKind::TarGz))
+        } else if name.ends_with(".tar") {
+            Ok(Some(ArchiveKind::Tar))
+        } else {
+            Err(CommonError::Archive(format!(
+                "{} is not a directory or a .zip, .tar or .tar.gz archive",
+                path.display()
+            )))
+        }
+    }
+}
+
+// Where the data of a file is inside its archive. For tar.gz the offset is in the decompressed stream
+#[derive(Clone, Copy, Debug)]
+pub struct ArchiveEntry {
+    offset: u64,
+    compressed_size: u64,
+    size: u64,
+    deflated: bool,
+}
+
+impl ArchiveEntry {
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+// A regular file of an archive, with its name as a relative path of the build
+pub struct ArchiveFile {
+    pub name: Vec<u8>,
+    pub entry: ArchiveEntry,
+}
+
+// Files of the archive in archive order. Directories and symlinks are left out, like in a build directory
+pub fn list_files(path: &Path, kind: ArchiveKind) -> Result<Vec<ArchiveFile>, CommonError> {
+    let mut files = match kind {
+        ArchiveKind::Zip => list_zip(path)?,
+        ArchiveKind::Tar => {
+            let mut archive = tar::Archive::new(BufReader::new(File::open(path)?));
+            list_tar(archive.entries_with_seek()?)?
+        }
+        ArchiveKind::TarGz => {
+            let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(File::open(path)?)));
+            list_tar(archive.entries()?)?
+        }
+    };
+
+    if let Some(stem) = archive_stem(path) {
+        strip_wrapper(&mut files, stem.as_bytes());
+    }
+    Ok(files)
+}
+
+fn list_zip(path: &Path) -> Result<Vec<ArchiveFile>, CommonError> {
+    let invalid = |e: zip::result::ZipError| {
+        CommonError::Archive(format!("{} is not a valid zip: {}", path.display(), e))
+    };
+
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?)).map_err(invalid)?;
+    let mut files = Vec::new();
+
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(invalid)?;
+        if file.is_dir() || file.is_symlink() {
+            continue;
+        }
+
+        let name = normalize(file.name_raw())?;
+        if file.encrypted() {
+            return Err(CommonError::Archive(format!(
+                "{} is encrypted",
+                String::from_utf8_lossy(&name)
+            )));
+        }
+
+        // The two methods every zip tool writes, we decompress them ourselves
+        let deflated = match file.compression() {
+            method if method == CompressionMethod::STORE => false,
+            method if method == CompressionMethod::DEFLATE => true,
+            method => {
+                return Err(CommonError::Archive(format!(
+                    "{} is compressed with {}, only stored and deflated entries can be read",
+                    String::from_utf8_lossy(&name),
+                    method
+                )));
+            }
+        };
+
+        files.push(ArchiveFile {
+            name,
+            entry: ArchiveEntry {
+                offset: file.data_start(),
+                compressed_size: file.compressed_size(),
+                size: file.size(),
+                deflated,
+            },
+        });
+    }
+
+    Ok(files)
+}
+
+fn list_tar<R: Read>(entries: tar::Entries<'_, R>) -> Result<Vec<ArchiveFile>, CommonError> {
+    let mut files = Vec::new();
+
+    for entry in entries {
+        let entry = entry?;
+        let entry_type = entry.header().entry_type();
+
+        // Their data is elsewhere or not stored as is, the file would go out wrong
+        if entry_type.is_hard_link() || entry_type.is_gnu_sparse() {
+            return Err(CommonError::Archive(format!(
+                "{} is a hard link or sparse file, which cannot be pushed from a tar",
+                String::from_utf8_lossy(&entry.path_bytes())
+            )));
+        }
+        if !entry_type.is_file() && !entry_type.is_contiguous() {
+            continue;
+        }
+
+        files.push(ArchiveFile {
+            name: normalize(&entry.path_bytes())?,
+            entry: ArchiveEntry {
+                offset: entry.raw_file_position(),
+                compressed_size: entry.size(),
+                size: entry.size(),
+                deflated: false,
+            },
+        });
+    }
+
+    Ok(files)
+}
+
+// Separators as in a fileindex, no leading ./ and nothing that points outside of the build
+fn normalize(name: &[u8]) -> Result<Vec<u8>, CommonError> {
+    let name: Vec<u8> = name
+        .iter()
+        .map(|&b| if b == b'\\' { b'/' } else { b })
+        .collect();
+
+    let mut components = Vec::new();
+    for component in name.split(|&b| b == b'/') {
+        match component {
+            b"" | b"." => continue,
+            b".." => {
+                return Err(CommonError::Archive(format!(
+                    "{} points outside of the build",
+                    String::from_utf8_lossy(&name)
+                )));
+            }
+            _ => components.push(component),
+        }
+    }
+
+    if name.starts_with(b"/") {
+        return Err(CommonError::Archive(format!(
+            "{} is an absolute path",
+            String::from_utf8_lossy(&name)
+        )));
+    }
+
+    Ok(components.join(&b'/'))
+}
+
+// File name of the archive without its extension, "build" for build.tar.gz
+fn archive_stem(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let lower = name.to_lowercase();
+    [".tar.gz", ".tgz", ".tar", ".zip"]
+        .iter()
+        .find(|extension| lower.ends_with(*extension))
+        .and_then(|extension| name.get(..name.len().checked_sub(extension.len())?))
+        .map(str::to_string)
+}
+
+// `zip -r build.zip build` puts everything in a folder named like the archive, which is not part
+// of the build. Any other single top folder is kept, it can be part of the build itself
+fn strip_wrapper(files: &mut [ArchiveFile], stem: &[u8]) {
+    let mut prefix = stem.to_vec();
+    prefix.push(b'/');
+
+    if !files.is_empty() && files.iter().all(|file| file.name.starts_with(&prefix)) {
+        for file in files {
+            file.name.drain(..prefix.len());
+        }
+    }
+}
+
+// Reads entries of one archive. Gzip cannot seek, so tar.gz entries read in archive order go on
+// from where the previous one stopped instead of decompressing everything before them again
+pub struct ArchiveReader {
+    path: PathBuf,
+    kind: ArchiveKind,
+    cursor: Mutex<Option<GzCursor>>,
+}
+
+struct GzCursor {
+    stream: GzDecoder<BufReader<File>>,
+    position: u64,
+}
+
+impl ArchiveReader {
+    pub fn new(path: impl Into<PathBuf>, kind: ArchiveKind) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+            cursor: Mutex::new(None),
+        }
+    }
+
+    // Entries are only cheap to read one after another, in archive order
+    pub fn is_sequential(&self) -> bool {
+        self.kind == ArchiveKind::TarGz
+    }
+
+    // Passes the data of the entry to `chunk` as it is read, until it returns false
+    pub fn read_entry(
+        &self,
+        entry: &ArchiveEntry,
+        mut chunk: impl FnMut(&[u8]) -> bool,
+    ) -> io::Result<()> {
+        if self.kind == ArchiveKind::TarGz {
+            return self.read_gz_entry(entry, &mut chunk);
+        }
+
+        let mut file = BufReader::new(File::open(&self.path)?);
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let raw = file.take(entry.compressed_size);
+
+        if entry.deflated {
+            read_chunks(&mut DeflateDecoder::new(raw), entry.size, &mut chunk)?;
+        } else {
+            read_chunks(&mut { raw }, entry.size, &mut chunk)?;
+        }
+        Ok(())
+    }
+
+    fn read_gz_entry(
+        &self,
+        entry: &ArchiveEntry,
+        chunk: &mut impl FnMut(&[u8]) -> bool,
+    ) -> io::Result<()> {
+        // A cursor past the entry (a retry, or entries read out of order) cannot go back
+        let cursor = self
+            .cursor
+            .lock()
+            .unwrap()
+            .take()
+            .filter(|cursor| cursor.position <= entry.offset);
+        let mut cursor = match cursor {
+            Some(cursor) => cursor,
+            None => GzCursor {
+                stream: GzDecoder::new(BufReader::new(File::open(&self.path)?)),
+                position: 0,
+            },
+        };
+
+        let skip = entry.offset - cursor.position;
+        let skipped = io::copy(&mut (&mut cursor.stream).take(skip), &mut io::sink())?;
+        if skipped != skip {
+            return Err(truncated());
+        }
+
+        // On errors the position is unknown and the cursor is dropped
+        let read = read_chunks(&mut cursor.stream, entry.size, chunk)?;
+        cursor.position = entry.offset + read;
+        *self.cursor.lock().unwrap() = Some(cursor);
+        Ok(())
+    }
+}
+
+// Reads `size` bytes unless `chunk` stops earlier, and returns how many were read
+fn read_chunks(
+    reader: &mut impl Read,
+    size: u64,
+    chunk: &mut impl FnMut(&[u8]) -> bool,
+) -> io::Result<u64> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut done: u64 = 0;
+
+    while done < size {
+        let to_read = (size - done).min(CHUNK_SIZE as u64) as usize;
+        let n = reader.read(&mut buffer[..to_read])?;
+        if n == 0 {
+            return Err(truncated());
+        }
+
+        done += n as u64;
+        if !chunk(&buffer[..n]) {
+            break;
+        }
+    }
+
+    Ok(done)
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "Archive entry is shorter than its size, the archive is truncated",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use flate2::{Compression, write::GzEncoder};
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::file_index::generate_fileindex;
+
+    const BUILD: [(&str, &[u8]); 3] = [
+        ("game.exe", b"MZ game"),
+        ("data/a.pak", b"first pack"),
+        ("data/sub/b.txt", b""),
+    ];
+
+    // Fresh directory under the system temp dir, one per test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clawdrop-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files(names: &[&str]) -> Vec<ArchiveFile> {
+        names
+            .iter()
+            .map(|name| ArchiveFile {
+                name: name.as_bytes().to_vec(),
+                entry: ArchiveEntry {
+                    offset: 0,
+                    compressed_size: 0,
+                    size: 0,
+                    deflated: false,
+                },
+            })
+            .collect()
+    }
+
+    fn names(files: &[ArchiveFile]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| String::from_utf8_lossy(&file.name).into_owned())
+            .collect()
+    }
+
+    fn write_zip(path: &Path, prefix: &str) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, content) in BUILD {
+            zip.start_file(format!("{}{}", prefix, name), options)
+                .unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, prefix: &str) {
+        let gz = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        for (name, content) in BUILD {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, format!("{}{}", prefix, name), content)
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn index_entries(path: &Path) -> Vec<(String, u64, String, String)> {
+        let mut entries: Vec<_> = generate_fileindex(path, &[])
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|f| (f.path, f.size, f.hash, f.content_type))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn stem_without_archive_extension() {
+        assert_eq!(archive_stem(Path::new("out/build.zip")).unwrap(), "build");
+        assert_eq!(
+            archive_stem(Path::new("Game-1.2.TAR.GZ")).unwrap(),
+            "Game-1.2"
+        );
+        assert_eq!(archive_stem(Path::new("game.tgz")).unwrap(), "game");
+        assert_eq!(archive_stem(Path::new("game.tar")).unwrap(), "game");
+        assert!(archive_stem(Path::new("game.7z")).is_none());
+    }
+
+    #[test]
+    fn strips_the_folder_named_like_the_archive() {
+        let mut archive = files(&["build/game.exe", "build/data/a.pak"]);
+        strip_wrapper(&mut archive, b"build");
+        assert_eq!(names(&archive), ["game.exe", "data/a.pak"]);
+    }
+
+    #[test]
+    fn keeps_other_single_folders() {
+        let mut archive = files(&["data/a.pak", "data/b.pak"]);
+        strip_wrapper(&mut archive, b"build");
+        assert_eq!(names(&archive), ["data/a.pak", "data/b.pak"]);
+
+        // Named like the archive, but not everything is inside
+        let mut archive = files(&["build/a.pak", "game.exe"]);
+        strip_wrapper(&mut archive, b"build");
+        assert_eq!(names(&archive), ["build/a.pak", "game.exe"]);
+
+        // Only a prefix of the folder name
+        let mut archive = files(&["build-old/a.pak"]);
+        strip_wrapper(&mut archive, b"build");
+        assert_eq!(names(&archive), ["build-old/a.pak"]);
+    }
+
+    #[test]
+    fn normalizes_names() {
+        assert_eq!(normalize(b"./data\\a.pak").unwrap(), b"data/a.pak");
+        assert_eq!(normalize(b"data//sub/./b.txt").unwrap(), b"data/sub/b.txt");
+        assert!(normalize(b"data/../../etc/passwd").is_err());
+        assert!(normalize(b"/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn archives_index_like_their_directory() {
+        let dir = temp_dir("archive-index");
+        let build = dir.join("build");
+        for (name, content) in BUILD {
+            let path = build.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let zip = dir.join("build.zip");
+        write_zip(&zip, "build/");
+        let tar_gz = dir.join("build.tar.gz");
+        write_tar_gz(&tar_gz, "");
+
+        let expected = index_entries(&build);
+        assert_eq!(expected.len(), BUILD.len());
+        assert_eq!(index_entries(&zip), expected);
+        assert_eq!(index_entries(&tar_gz), expected);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn archive_with_another_folder_keeps_it() {
+        let dir = temp_dir("archive-folder");
+        let zip = dir.join("artifact.zip");
+        write_zip(&zip, "build/");
+
+        let paths: Vec<String> = index_entries(&zip).into_iter().map(|e| e.0).collect();
+        assert_eq!(
+            paths,
+            ["build/data/a.pak", "build/data/sub/b.txt", "build/game.exe"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
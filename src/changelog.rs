@@ -0,0 +1,237 @@
+use std::process::Command;
+
+use crate::{errors::post::PostError, pusher::PushSummary, utils::format_bytes};
+
+// Commit subject split into its conventional-commit parts when it has them
+pub struct Commit {
+    pub hash: String,
+    pub kind: Option<String>,
+    pub description: String,
+}
+
+impl Commit {
+    fn parse(hash: &str, subject: &str) -> Self {
+        // type(scope)!: description
+        let conventional = subject.split_once(": ").and_then(|(head, description)| {
+            let kind = head.trim_end_matches('!');
+            let kind = kind.split_once('(').map_or(kind, |(kind, _)| kind);
+            let valid = !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphanumeric());
+            valid.then(|| (kind.to_lowercase(), description.trim().to_string()))
+        });
+
+        match conventional {
+            Some((kind, description)) => Commit {
+                hash: hash.to_string(),
+                kind: Some(kind),
+                description,
+            },
+            None => Commit {
+                hash: hash.to_string(),
+                kind: None,
+                description: subject.trim().to_string(),
+            },
+        }
+    }
+}
+
+// Range from the tag before HEAD to HEAD, skipping a tag placed on HEAD itself (the one being released)
+pub fn default_range() -> Result<String, PostError> {
+    let head = git(&["rev-parse", "HEAD"])?;
+
+    let Ok(latest) = git(&["describe", "--tags", "--abbrev=0", "HEAD"]) else {
+        return Ok("HEAD".to_string());
+    };
+
+    let latest_commit = git(&["rev-list", "-n", "1", &latest])?;
+    if latest_commit != head {
+        return Ok(format!("{}..HEAD", latest));
+    }
+
+    match git(&["describe", "--tags", "--abbrev=0", &format!("{}^", latest)]) {
+        Ok(previous) => Ok(format!("{}..{}", previous, latest)),
+        Err(_) => Ok(latest),
+    }
+}
+
+// Commits in a git range (like v1.0..v1.1), oldest first, merges skipped.
+// With kinds only conventional commits of those types are kept
+pub fn commits_in_range(range: &str, kinds: &[String]) -> Result<Vec<Commit>, PostError> {
+    let log = git(&[
+        "log",
+        "--no-merges",
+        "--reverse",
+        "--format=%h%x1f%s",
+        range,
+        "--",
+    ])?;
+
+    let commits: Vec<Commit> = log
+        .lines()
+        .filter_map(|line| line.split_once('\u{1f}'))
+        .map(|(hash, subject)| Commit::parse(hash, subject))
+        .filter(|commit| {
+            kinds.is_empty()
+                || commit
+                    .kind
+                    .as_ref()
+                    .is_some_and(|kind| kinds.iter().any(|k| k.eq_ignore_ascii_case(kind)))
+        })
+        .collect();
+
+    if commits.is_empty() {
+        return Err(PostError::EmptyChangelog(range.to_string()));
+    }
+
+    Ok(commits)
+}
+
+// Markdown body listing the commits grouped by type, plus the build summary after a push
+pub fn render(commits: &[Commit], push: Option<&PushSummary>) -> String {
+    let mut body = String::new();
+
+    if let Some(push) = push {
+        body.push_str(&format!(
+            "Version **{}** is now live for {}.\n\n",
+            push.version, push.os
+        ));
+    }
+
+    let sections = [
+        ("feat", "New features"),
+        ("fix", "Fixes"),
+        ("perf", "Performance"),
+    ];
+    for (kind, heading) in sections {
+        let entries: Vec<&Commit> = commits
+            .iter()
+            .filter(|c| c.kind.as_deref() == Some(kind))
+            .collect();
+        push_section(&mut body, heading, &entries);
+    }
+
+    let other: Vec<&Commit> = commits
+        .iter()
+        .filter(|c| {
+            !sections
+                .iter()
+                .any(|(kind, _)| c.kind.as_deref() == Some(*kind))
+        })
+        .collect();
+    push_section(&mut body, "Other changes", &other);
+
+    if let Some(push) = push {
+        let changes = &push.changes;
+        let sign = if push.size_delta < 0 { "-" } else { "+" };
+
+        body.push_str("## Build\n\n");
+        body.push_str(&format!(
+            "- {} files added, {} changed, {} removed\n",
+            changes.new_files.len(),
+            changes.modified_files.len(),
+            changes.deleted_files.len()
+        ));
+        body.push_str(&format!(
+            "- Download size: {} ({}{})\n",
+            format_bytes(push.total_bytes),
+            sign,
+            format_bytes(push.size_delta.unsigned_abs())
+        ));
+    }
+
+    body
+}
+
+fn push_section(body: &mut String, heading: &str, commits: &[&Commit]) {
+    if commits.is_empty() {
+        return;
+    }
+
+    body.push_str(&format!("## {}\n\n", heading));
+    for commit in commits {
+        body.push_str(&format!(
+            "- {} ({})\n",
+            capitalize(&commit.description),
+            commit.hash
+        ));
+    }
+    body.push('\n');
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn git(args: &[&str]) -> Result<String, PostError> {
+    let output = Command::new("git").args(args).output()?;
+
+    if !output.status.success() {
+        return Err(PostError::Git {
+            command: args.join(" "),
+            message: String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(subject: &str) -> (Option<String>, String) {
+        let commit = Commit::parse("abc1234", subject);
+        (commit.kind, commit.description)
+    }
+
+    #[test]
+    fn conventional_subjects_are_split() {
+        assert_eq!(
+            parts("feat: add controller support"),
+            (Some("feat".into()), "add controller support".into())
+        );
+        assert_eq!(
+            parts("Fix(input)!:  rebindable keys "),
+            (Some("fix".into()), "rebindable keys".into())
+        );
+        assert_eq!(
+            parts("perf(render)(gl): batch draws"),
+            (Some("perf".into()), "batch draws".into())
+        );
+    }
+
+    #[test]
+    fn other_subjects_keep_their_text() {
+        assert_eq!(parts("Update README"), (None, "Update README".into()));
+        assert_eq!(
+            parts("Release notes: 1.2"),
+            (None, "Release notes: 1.2".into())
+        );
+        assert_eq!(parts(": empty type"), (None, ": empty type".into()));
+        assert_eq!(parts("wip:no space"), (None, "wip:no space".into()));
+    }
+
+    #[test]
+    fn render_groups_by_type() {
+        let commits = [
+            Commit::parse("a1", "fix: crash on start"),
+            Commit::parse("b2", "feat(ui): dark mode"),
+            Commit::parse("c3", "Bump assets"),
+            Commit::parse("d4", "chore: tidy"),
+        ];
+
+        assert_eq!(
+            render(&commits, None),
+            "## New features\n\n- Dark mode (b2)\n\n\
+             ## Fixes\n\n- Crash on start (a1)\n\n\
+             ## Other changes\n\n- Bump assets (c3)\n- Tidy (d4)\n\n"
+        );
+    }
+}
@@ -0,0 +1,812 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use reqwest::multipart;
+use serde::{Deserialize, Serialize};
+use tracing::{Instrument, debug, debug_span, trace};
+
+use crate::{
+    constants::{
+        AUTH_POLL_URL, AUTH_REQUEST_URL, COMPLETE_PUSH_URL, GAME_DETAILS_URL, GAME_POST_IMAGE_URL,
+        GAME_POST_ITEM_URL, GAME_POST_PUBLISH_URL, GAME_POST_URL, GAMES_LIST_URL,
+        REQUEST_UPLOAD_URL, VERIFY_API_KEY_URL, VERIFY_UPLOAD_URL,
+    },
+    errors::{
+        api_key::ApiKeyError,
+        auth::AuthError,
+        common::CommonError,
+        network::{NetworkError, ResponseInfo},
+        post::PostError,
+        push::{PushError, S3Failure},
+    },
+    file_index::FileIndex,
+    network::build_client,
+};
+
+/// An object of a live build as S3 describes it.
+pub struct RemoteObject {
+    pub size: Option<u64>,
+    /// Base64 SHA-256, missing for objects uploaded without a checksum or in parts.
+    pub checksum_sha256: Option<String>,
+}
+
+/// Objects of a live build sit next to its fileindex.json.
+pub fn live_object_url(fileindex_url: &reqwest::Url, path: &str) -> reqwest::Url {
+    let mut url = fileindex_url.clone();
+    url.set_query(None);
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop().extend(path.split('/'));
+    }
+    url
+}
+
+/// Async client for the Raccreative Games API, every request to it goes through here.
+#[derive(Clone)]
+pub struct RaccreativeClient {
+    http: reqwest::Client,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyApiKeyQuery<'a> {
+    api_key: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyApiKeyResponse {
+    valid: bool,
+}
+
+/// Games the API key owner develops.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameListResponse {
+    pub games: Vec<Game>,
+}
+
+impl GameListResponse {
+    /// Game by numeric id or url slug.
+    pub fn find(self, id: &str) -> Option<Game> {
+        match id.parse::<u64>() {
+            Ok(parsed_id) => self.games.into_iter().find(|g| g.id == parsed_id),
+            Err(_) => self
+                .games
+                .into_iter()
+                .find(|g| g.url_identifier.as_deref() == Some(id)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Game {
+    pub id: u64,
+    pub title: String,
+    pub url_identifier: Option<String>,
+    pub windows_version: Option<String>,
+    pub linux_version: Option<String>,
+    pub mac_version: Option<String>,
+    pub html_version: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl Game {
+    /// Live version for `windows`, `linux`, `mac` or `html`.
+    pub fn version_for(&self, os: &str) -> Option<&String> {
+        match os {
+            "windows" => self.windows_version.as_ref(),
+            "linux" => self.linux_version.as_ref(),
+            "mac" => self.mac_version.as_ref(),
+            "html" => self.html_version.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// One game with its live builds.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameDetails {
+    pub id: u64,
+    pub title: String,
+    pub url: Option<String>,
+    pub last_pushed_at: Option<String>,
+    #[serde(default)]
+    pub builds: Vec<GameBuild>,
+}
+
+/// Live build of a platform.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameBuild {
+    pub os: String,
+    pub version: String,
+    pub size: Option<u64>,
+    pub pushed_at: Option<String>,
+    /// Download URL of the live fileindex.json.
+    pub fileindex: Option<String>,
+    pub original_zip_name: Option<String>,
+}
+
+/// Pending browser authorization, poll it with [`RaccreativeClient::poll_auth`].
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthRequest {
+    pub poll_token: String,
+    pub verify_url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PollQuery<'a> {
+    poll_token: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollResponse {
+    status: String,
+    api_key: Option<String>,
+}
+
+/// State of a pending browser authorization.
+pub enum AuthStatus {
+    Pending,
+    Authorized(String),
+}
+
+/// News post for a game. The cover is sent as a multipart file part.
+pub struct NewPost {
+    pub game_id: u64,
+    pub title: String,
+    pub body: String,
+    pub slug: Option<String>,
+    pub cover: Option<PostImage>,
+    pub tags: Vec<String>,
+    /// Saved without publishing, see [`RaccreativeClient::publish_post`].
+    pub draft: bool,
+    /// Scheduled publication, the post is sent as a draft with this date.
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// Changes to an existing post, `None` fields are left as they are.
+#[derive(Default)]
+pub struct PostUpdate {
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub slug: Option<String>,
+    pub cover: Option<PostImage>,
+}
+
+pub struct PostImage {
+    pub file_name: String,
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// News post as returned by the API.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Post {
+    pub id: u64,
+    pub title: String,
+    pub slug: Option<String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+    pub published_at: Option<String>,
+    /// Set when the server scheduled the post.
+    pub publish_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostImageResponse {
+    url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostListQuery {
+    game_id: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostListResponse {
+    posts: Vec<Post>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporaryCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub expiration: String,
+    pub session_token: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub region: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestUploadResponse {
+    pub upload_credentials: TemporaryCredentials,
+    pub delete_credentials: TemporaryCredentials,
+    pub extra_uploads: ExtraUploads,
+    pub extra_downloads: ExtraDownloads,
+    pub original_zip_name: Option<String>,
+    pub upload_id: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraUploads {
+    pub manifest: String,
+    pub fileindex: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraDownloads {
+    pub fileindex: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestUploadBody {
+    version: String,
+    fileindex: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyUploadBody {
+    version: String,
+    upload_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletePushBody {
+    pub os: String,
+    pub new_version: String,
+    pub file_name: String,
+}
+
+impl RaccreativeClient {
+    /// Client without API key, only auth requests are available.
+    ///
+    /// Uses the proxy, CA bundle and timeouts from [`set_network_settings`](crate::network::set_network_settings).
+    pub fn new() -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            http: build_client()?,
+            api_key: None,
+        })
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Underlying HTTP client, shared with presigned URL transfers.
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    fn api_key(&self) -> Result<&str, ApiKeyError> {
+        self.api_key.as_deref().ok_or(ApiKeyError::MissingEnv)
+    }
+
+    // Every request goes through here so the logs show which call failed. Only host and path are
+    // logged, query strings carry the API key or presigned signatures
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let request = request.build()?;
+        let url = request.url();
+        let span = debug_span!(
+            "http",
+            method = %request.method(),
+            url = %format!("{}{}", url.host_str().unwrap_or_default(), url.path()),
+        );
+
+        async move {
+            trace!(headers = ?redacted_headers(request.headers()), "sending request");
+            let start = Instant::now();
+            let result = self.http.execute(request).await;
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+
+            match &result {
+                Ok(res) => debug!(status = res.status().as_u16(), elapsed_ms, "response"),
+                // The innermost cause never has the URL, the outer error does
+                Err(e) => debug!(error = %root_cause(e), elapsed_ms, "request failed"),
+            }
+
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Whether the key is valid. Errors never contain the request URL, it carries the key.
+    pub async fn verify_api_key(&self, key: &str) -> Result<bool, NetworkError> {
+        let res = self
+            .send(
+                self.http
+                    .get(VERIFY_API_KEY_URL)
+                    .query(&VerifyApiKeyQuery { api_key: key }),
+            )
+            .await
+            .map_err(reqwest::Error::without_url)?;
+
+        let res = match check_status(res).await {
+            Ok(res) => res,
+            Err(NetworkError::Unauthorized(_) | NetworkError::Forbidden(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let response: VerifyApiKeyResponse =
+            res.json().await.map_err(reqwest::Error::without_url)?;
+
+        Ok(response.valid)
+    }
+
+    /// Starts a browser authorization, the user must open `verify_url`.
+    pub async fn request_auth(&self) -> Result<AuthRequest, AuthError> {
+        let res = self.send(self.http.post(AUTH_REQUEST_URL)).await?;
+
+        Ok(check_status(res).await?.json().await?)
+    }
+
+    pub async fn poll_auth(&self, poll_token: &str) -> Result<AuthStatus, AuthError> {
+        let res = self
+            .send(
+                self.http
+                    .get(AUTH_POLL_URL)
+                    .query(&PollQuery { poll_token }),
+            )
+            .await?;
+
+        let poll_res: PollResponse = match check_status(res).await {
+            Ok(res) => res.json().await?,
+            Err(NetworkError::NotFound(_)) => return Err(AuthError::InvalidTokenResponse),
+            Err(e) => return Err(e.into()),
+        };
+
+        match poll_res.status.as_str() {
+            "pending" => Ok(AuthStatus::Pending),
+            "authorized" => poll_res
+                .api_key
+                .map(AuthStatus::Authorized)
+                .ok_or(AuthError::MissingApiKeyInResponse),
+            "expired" => Err(AuthError::RequestExpired),
+            "null" => Err(AuthError::InvalidTokenResponse),
+            _ => Err(AuthError::UnknownStatus(poll_res.status)),
+        }
+    }
+
+    /// Games the API key owner can push builds and posts to.
+    pub async fn list_games(&self) -> Result<GameListResponse, NetworkError> {
+        let api_key = self.api_key().map_err(|_| NetworkError::InvalidApiKey)?;
+
+        let res = self
+            .send(self.http.get(GAMES_LIST_URL).header("x-api-key", api_key))
+            .await?;
+
+        Ok(check_status(res)
+            .await
+            .map_err(forbidden_as_invalid_key)?
+            .json()
+            .await?)
+    }
+
+    /// Game with its live builds, only for games the API key owner develops.
+    pub async fn game_details(&self, id: u64) -> Result<GameDetails, NetworkError> {
+        let api_key = self.api_key().map_err(|_| NetworkError::InvalidApiKey)?;
+
+        let res = self
+            .send(
+                self.http
+                    .get(GAME_DETAILS_URL.replace("{id}", &id.to_string()))
+                    .header("x-api-key", api_key),
+            )
+            .await?;
+
+        Ok(check_status(res)
+            .await
+            .map_err(forbidden_as_invalid_key)?
+            .json()
+            .await?)
+    }
+
+    /// Creates a post, published right away unless it is a draft.
+    pub async fn create_post(&self, post: NewPost) -> Result<Post, PostError> {
+        let api_key = self.api_key()?;
+
+        let mut form = multipart::Form::new()
+            .text("gameId", post.game_id.to_string())
+            .text("title", post.title)
+            .text("body", post.body);
+
+        if let Some(s) = post.slug {
+            form = form.text("slug", s);
+        }
+
+        for tag in post.tags {
+            form = form.text("tags", tag);
+        }
+
+        // A scheduled post stays a draft until then, even if the server ignores publishAt
+        if post.draft || post.publish_at.is_some() {
+            form = form.text("draft", "true");
+        }
+
+        if let Some(publish_at) = post.publish_at {
+            form = form.text("publishAt", publish_at.to_rfc3339());
+        }
+
+        form = with_cover(form, post.cover)?;
+
+        let res = self
+            .send(
+                self.http
+                    .post(GAME_POST_URL)
+                    .header("x-api-key", api_key)
+                    .multipart(form),
+            )
+            .await?;
+
+        Ok(check_post_response(res, None).await?.json().await?)
+    }
+
+    /// Hosts an image for a post body and returns its public URL.
+    pub async fn upload_post_image(
+        &self,
+        game_id: u64,
+        image: PostImage,
+    ) -> Result<String, PostError> {
+        let api_key = self.api_key()?;
+
+        let part = multipart::Part::bytes(image.bytes)
+            .file_name(image.file_name)
+            .mime_str(&image.mime_type)?;
+        let form = multipart::Form::new()
+            .text("gameId", game_id.to_string())
+            .part("image", part);
+
+        let res = self
+            .send(
+                self.http
+                    .post(GAME_POST_IMAGE_URL)
+                    .header("x-api-key", api_key)
+                    .multipart(form),
+            )
+            .await?;
+
+        let response: PostImageResponse = check_post_response(res, None).await?.json().await?;
+        Ok(response.url)
+    }
+
+    /// Posts of a game, drafts included.
+    pub async fn list_posts(&self, game_id: u64) -> Result<Vec<Post>, PostError> {
+        let api_key = self.api_key()?;
+
+        let res = self
+            .send(
+                self.http
+                    .get(GAME_POST_URL)
+                    .header("x-api-key", api_key)
+                    .query(&PostListQuery { game_id }),
+            )
+            .await?;
+
+        let response: PostListResponse = check_post_response(res, None).await?.json().await?;
+
+        Ok(response.posts)
+    }
+
+    pub async fn update_post(&self, post_id: u64, update: PostUpdate) -> Result<Post, PostError> {
+        let api_key = self.api_key()?;
+
+        let mut form = multipart::Form::new();
+        if let Some(title) = update.title {
+            form = form.text("title", title);
+        }
+        if let Some(body) = update.body {
+            form = form.text("body", body);
+        }
+        if let Some(slug) = update.slug {
+            form = form.text("slug", slug);
+        }
+        form = with_cover(form, update.cover)?;
+
+        let res = self
+            .send(
+                self.http
+                    .patch(GAME_POST_ITEM_URL.replace("{id}", &post_id.to_string()))
+                    .header("x-api-key", api_key)
+                    .multipart(form),
+            )
+            .await?;
+
+        Ok(check_post_response(res, Some(post_id))
+            .await?
+            .json()
+            .await?)
+    }
+
+    pub async fn delete_post(&self, post_id: u64) -> Result<(), PostError> {
+        let api_key = self.api_key()?;
+
+        let res = self
+            .send(
+                self.http
+                    .delete(GAME_POST_ITEM_URL.replace("{id}", &post_id.to_string()))
+                    .header("x-api-key", api_key),
+            )
+            .await?;
+
+        check_post_response(res, Some(post_id)).await?;
+        Ok(())
+    }
+
+    /// Publishes a draft post.
+    pub async fn publish_post(&self, post_id: u64) -> Result<Post, PostError> {
+        let api_key = self.api_key()?;
+
+        let res = self
+            .send(
+                self.http
+                    .put(GAME_POST_PUBLISH_URL.replace("{id}", &post_id.to_string()))
+                    .header("x-api-key", api_key),
+            )
+            .await?;
+
+        Ok(check_post_response(res, Some(post_id))
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// Opens a differential upload and returns temporary S3 credentials for it.
+    pub async fn request_upload(
+        &self,
+        id: u64,
+        os: &str,
+        fileindex: &FileIndex,
+    ) -> Result<RequestUploadResponse, PushError> {
+        let api_key = self.api_key()?;
+
+        let body = RequestUploadBody {
+            version: os.to_string(),
+            fileindex: serde_json::to_string(fileindex).map_err(CommonError::Encode)?,
+        };
+
+        let res = self
+            .send(
+                self.http
+                    .post(REQUEST_UPLOAD_URL.replace("{id}", &id.to_string()))
+                    .header("x-api-key", api_key)
+                    .json(&body),
+            )
+            .await?;
+
+        // The server answers a build over the size limit with 400
+        if res.status() == reqwest::StatusCode::BAD_REQUEST {
+            return Err(PushError::FileSizeLimitReach);
+        }
+
+        Ok(check_status(res).await?.json().await?)
+    }
+
+    /// Live fileindex of a build, empty when the platform has never been pushed.
+    pub async fn download_fileindex(&self, url: &str) -> Result<FileIndex, NetworkError> {
+        let res = self.send(self.http.get(url)).await?;
+
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(FileIndex::default());
+        }
+
+        Ok(check_status(res).await?.json().await?)
+    }
+
+    /// Size and stored SHA-256 of one object of a live build, `None` if it does not exist.
+    pub async fn head_live_object(
+        &self,
+        url: reqwest::Url,
+    ) -> Result<Option<RemoteObject>, NetworkError> {
+        // S3 only returns the stored checksum when asked for it
+        let res = self
+            .send(self.http.head(url).header("x-amz-checksum-mode", "ENABLED"))
+            .await?;
+
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let res = check_status(res).await?;
+        let header = |name: &str| {
+            res.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Ok(Some(RemoteObject {
+            size: header("content-length").and_then(|size| size.parse().ok()),
+            // A multipart object has a checksum of its part checksums ("...-3"), not of the file
+            checksum_sha256: header("x-amz-checksum-sha256")
+                .filter(|checksum| !checksum.contains('-')),
+        }))
+    }
+
+    /// Uploads a JSON document to a presigned URL.
+    pub async fn put_presigned_json(
+        &self,
+        url: &str,
+        name: &str,
+        json: Vec<u8>,
+    ) -> Result<(), PushError> {
+        let res = self
+            .send(
+                self.http
+                    .put(url)
+                    .header("content-type", "application/json")
+                    .body(json),
+            )
+            .await
+            .map_err(|e| {
+                PushError::S3Error(
+                    S3Failure::new(format!("Failed uploading {}", name)).with_source(e),
+                )
+            })?;
+
+        check_status(res).await.map_err(|e| {
+            let mut failure = S3Failure::new(format!("Failed uploading {}: {}", name, e));
+            failure.request_id = e.response().and_then(|r| r.request_id.clone());
+            PushError::S3Error(failure)
+        })?;
+
+        Ok(())
+    }
+
+    /// Asks the server to check the uploaded files against the requested fileindex.
+    pub async fn verify_upload(&self, id: u64, os: &str, upload_id: &str) -> Result<(), PushError> {
+        let api_key = self.api_key()?;
+
+        let verify_body = VerifyUploadBody {
+            version: os.to_string(),
+            upload_id: upload_id.to_string(),
+        };
+
+        let verify_res = self
+            .send(
+                self.http
+                    .put(VERIFY_UPLOAD_URL.replace("{id}", &id.to_string()))
+                    .header("x-api-key", api_key)
+                    .json(&verify_body),
+            )
+            .await?;
+
+        if verify_res.status() == reqwest::StatusCode::BAD_REQUEST {
+            return Err(PushError::FileindexMismatch);
+        }
+
+        check_status(verify_res).await?;
+        Ok(())
+    }
+
+    /// Publishes the new version and closes the upload.
+    pub async fn complete_push(&self, id: u64, body: &CompletePushBody) -> Result<(), PushError> {
+        let api_key = self.api_key()?;
+
+        let complete_push_res = self
+            .send(
+                self.http
+                    .put(COMPLETE_PUSH_URL.replace("{id}", &id.to_string()))
+                    .header("x-api-key", api_key)
+                    .json(body),
+            )
+            .await?;
+
+        check_status(complete_push_res).await?;
+        Ok(())
+    }
+}
+
+fn with_cover(
+    form: multipart::Form,
+    cover: Option<PostImage>,
+) -> Result<multipart::Form, PostError> {
+    let Some(cover) = cover else {
+        return Ok(form);
+    };
+
+    let part = multipart::Part::bytes(cover.bytes)
+        .file_name(cover.file_name)
+        .mime_str(&cover.mime_type)?;
+
+    Ok(form.part("cover", part))
+}
+
+fn redacted_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = match name.as_str() {
+                "x-api-key" | "authorization" => "<redacted>".to_string(),
+                _ => value.to_str().unwrap_or("<binary>").to_string(),
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn root_cause(error: &dyn std::error::Error) -> &dyn std::error::Error {
+    let mut cause = error;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    cause
+}
+
+// The one place HTTP statuses become errors, endpoints only add what a status means for them
+async fn check_status(res: reqwest::Response) -> Result<reqwest::Response, NetworkError> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+
+    let header = |name: &str| {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    };
+    let retry_after = header("retry-after").and_then(|value| value.parse().ok());
+    // S3 presigned URLs answer with x-amz-request-id, the API with x-request-id
+    let request_id = header("x-request-id").or_else(|| header("x-amz-request-id"));
+
+    let response = ResponseInfo {
+        status: status.as_u16(),
+        request_id,
+        body: res.text().await.unwrap_or_else(|_| "<no body>".into()),
+    };
+
+    Err(match status {
+        reqwest::StatusCode::UNAUTHORIZED => NetworkError::Unauthorized(response),
+        reqwest::StatusCode::FORBIDDEN => NetworkError::Forbidden(response),
+        reqwest::StatusCode::NOT_FOUND => NetworkError::NotFound(response),
+        reqwest::StatusCode::PAYLOAD_TOO_LARGE => NetworkError::PayloadTooLarge(response),
+        reqwest::StatusCode::TOO_MANY_REQUESTS => NetworkError::RateLimited {
+            retry_after,
+            response,
+        },
+        _ => NetworkError::Status(response),
+    })
+}
+
+// Endpoints that only need the API key answer an unknown one with 403
+fn forbidden_as_invalid_key(e: NetworkError) -> NetworkError {
+    match e {
+        NetworkError::Forbidden(response) => NetworkError::Unauthorized(response),
+        e => e,
+    }
+}
+
+// 404 only means a missing post when one was addressed
+async fn check_post_response(
+    res: reqwest::Response,
+    post_id: Option<u64>,
+) -> Result<reqwest::Response, PostError> {
+    check_status(res).await.map_err(|e| match (e, post_id) {
+        (NetworkError::NotFound(_), Some(id)) => PostError::PostNotFound(id.to_string()),
+        (e, _) => e.into(),
+    })
+}
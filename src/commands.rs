@@ -1,34 +1,73 @@
-use crate::{cli::Cli, red, utils::check_for_updates};
+use clawdrop::{
+    config::load_project_config,
+    errors::{common::CommonError, verify::VerifyError},
+};
+
+use crate::{cli::Cli, commands::upgrade::check_for_updates, report::ErrorReport};
 
 pub mod auth;
+pub mod completions;
 pub mod doctor;
+pub mod index;
 pub mod list;
 pub mod logout;
+pub mod man;
 pub mod post;
 pub mod push;
 pub mod set;
+pub mod status;
 pub mod unset;
 pub mod upgrade;
+pub mod verify;
+pub mod watch;
 pub mod whereis;
 
-pub fn dispatch(cli: Cli) {
+pub async fn dispatch(cli: Cli, report: ErrorReport) {
     match cli.command {
         Some(crate::cli::Commands::Doctor) => {
-            if let Err(e) = doctor::run() {
-                eprintln!("Error in doctor: {}", e);
-                std::process::exit(1);
+            if let Err(e) = doctor::run().await {
+                report.exit("Error in doctor", &e);
             }
         }
         Some(crate::cli::Commands::Whereis) => {
             if let Err(e) = whereis::run() {
-                eprintln!("Error in whereis: {}", e);
-                std::process::exit(1);
+                report.exit("Error in whereis", &CommonError::from(e));
+            }
+        }
+        Some(crate::cli::Commands::Completions {
+            shell,
+            static_script,
+        }) => {
+            if let Err(e) = completions::run(shell, static_script) {
+                report.exit("Error in completions", &CommonError::from(e));
+            }
+        }
+        Some(crate::cli::Commands::Man) => {
+            if let Err(e) = man::run() {
+                report.exit("Error in man", &CommonError::from(e));
+            }
+        }
+        Some(crate::cli::Commands::Index { command }) => {
+            let result = match command {
+                crate::cli::IndexCommands::Create {
+                    path,
+                    output,
+                    ignore,
+                } => index::create(path, output, ignore),
+                crate::cli::IndexCommands::Verify {
+                    path,
+                    index,
+                    ignore,
+                } => index::verify(path, index, ignore),
+            };
+
+            if let Err(e) = result {
+                report.exit("Error in index", &e);
             }
         }
         Some(crate::cli::Commands::Upgrade) => {
-            if let Err(e) = upgrade::run() {
-                eprintln!("Error in upgrade: {}", e);
-                std::process::exit(1);
+            if let Err(e) = upgrade::run().await {
+                report.exit("Error in upgrade", &e);
             }
         }
         Some(crate::cli::Commands::Auth {
@@ -36,49 +75,85 @@ pub fn dispatch(cli: Cli) {
             headless,
             key,
         }) => {
-            if let Err(e) = auth::run(force, headless, key) {
-                eprintln!("Error in auth: {}", e);
-                std::process::exit(1);
+            if let Err(e) = auth::run(force, headless, key).await {
+                report.exit("Error in auth", &e);
             }
         }
         Some(crate::cli::Commands::Logout) => {
             if let Err(e) = logout::run() {
-                eprintln!("Error in logout: {}", e);
-                std::process::exit(1);
+                report.exit("Error in logout", &CommonError::from(e));
             }
         }
-        Some(crate::cli::Commands::List) => {
-            if let Err(e) = list::run() {
-                eprintln!("Error in list: {}", e);
-                std::process::exit(1);
+        Some(crate::cli::Commands::List {
+            filter,
+            sort,
+            os,
+            columns,
+            refresh,
+        }) => {
+            if let Err(e) = list::run(filter, sort, os, columns, refresh).await {
+                report.exit("Error in list", &e);
             }
         }
-        Some(crate::cli::Commands::Set { id }) => {
-            if let Err(e) = set::run(id) {
-                eprintln!("Error in set: {}", e);
-                std::process::exit(1);
+        Some(crate::cli::Commands::Info { id, refresh }) => {
+            if let Err(e) = list::info(id, refresh).await {
+                report.exit("Error in info", &e);
+            }
+        }
+        Some(crate::cli::Commands::Set { id, refresh }) => {
+            if let Err(e) = set::run(id, refresh).await {
+                report.exit("Error in set", &e);
+            }
+        }
+        Some(crate::cli::Commands::Status) => {
+            if let Err(e) = status::run().await {
+                report.exit("Error in status", &e);
+            }
+        }
+        Some(crate::cli::Commands::Verify { remote, id, os }) => {
+            let result = if remote {
+                verify::remote(id, os).await
+            } else {
+                Err(VerifyError::NoCheckSelected)
+            };
+            if let Err(e) = result {
+                report.exit("Error in verify", &e);
             }
         }
         Some(crate::cli::Commands::Unset) => {
             if let Err(e) = unset::run() {
-                eprintln!("Error in unset: {}", e);
-                std::process::exit(1);
+                report.exit("Error in unset", &CommonError::from(e));
             }
         }
-        Some(crate::cli::Commands::Post {
-            id,
-            title,
-            body,
-            cover,
-            slug,
-        }) => {
-            if let Err(e) = check_for_updates() {
+        Some(crate::cli::Commands::Post { command, create }) => {
+            if let Err(e) = check_for_updates().await {
                 eprintln!("Warning: failed to check for updates: {}", e);
             }
 
-            if let Err(e) = post::run(id, title, body, cover, slug) {
-                eprintln!("Error in post: {}", e);
-                std::process::exit(1);
+            let result = match (command, create) {
+                (Some(crate::cli::PostCommands::Create(args)), _) | (None, Some(args)) => {
+                    post::run(args, None).await
+                }
+                (Some(crate::cli::PostCommands::List { id }), _) => post::list(id).await,
+                (Some(crate::cli::PostCommands::Edit(args)), _) => post::edit(args).await,
+                (
+                    Some(crate::cli::PostCommands::Delete {
+                        post: post_ref,
+                        id,
+                        yes,
+                    }),
+                    _,
+                ) => post::delete(post_ref, id, yes).await,
+                (Some(crate::cli::PostCommands::Publish { post: post_ref, id }), _) => {
+                    post::publish(post_ref, id).await
+                }
+                (Some(crate::cli::PostCommands::Flush), _) => post::flush().await,
+                // Without a subcommand clap requires --title and --body (or --from/--changelog)
+                (None, None) => unreachable!("post arguments are enforced by clap"),
+            };
+
+            if let Err(e) = result {
+                report.exit("Error in post", &e);
             }
         }
         Some(crate::cli::Commands::Push {
@@ -91,27 +166,79 @@ pub fn dispatch(cli: Cli) {
             shorthand,
             version,
             force,
+            index,
+            index_sample,
+            concurrency,
+            limit_rate,
+            watch,
+            quiet_period,
+            wait_stable,
+            skip_unreadable,
+            non_utf8,
+            announce,
+            commit_types,
         }) => {
-            if let Err(e) = check_for_updates() {
+            if let Err(e) = check_for_updates().await {
                 eprintln!("Warning: failed to check for updates: {}", e);
             }
 
+            // Flags first, then clawdrop.json in the working directory
+            let project = match load_project_config() {
+                Ok(project) => project,
+                Err(e) => report.exit("Error reading clawdrop.json", &e),
+            };
+
+            // The changelog is resolved up front, a bad range should not leave a build live without its post
+            let announcement = match announce
+                .map(|range| post::prepare_announcement(range, &commit_types))
+                .transpose()
+            {
+                Ok(announcement) => announcement,
+                Err(e) => report.exit("Error preparing the announcement", &e),
+            };
+
             let args = push::PushArgs {
-                id,
-                os,
-                exe,
+                id: id.or(project.id),
+                os: os.or(project.os),
+                exe: exe.or(project.exe),
                 version,
-                path,
-                ignore,
+                path: path.or(project.path).unwrap_or_else(|| ".".to_string()),
+                ignore: if ignore.is_empty() {
+                    project.ignore
+                } else {
+                    ignore
+                },
                 no_bump,
                 shorthand,
                 force,
+                index,
+                index_sample,
+                concurrency,
+                limit_rate,
+                wait_stable,
+                skip_unreadable,
+                non_utf8,
+            };
+            let result = if watch {
+                watch::run(args, quiet_period).await
+            } else {
+                match push::run(args).await {
+                    Ok(summary) => {
+                        if let Some(announcement) = announcement
+                            && let Err(e) = post::announce(&summary, announcement).await
+                        {
+                            eprintln!();
+                            report.exit("Build is live but the announcement failed", &e);
+                        }
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                }
             };
-            // Tokio async runtime for this command
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            if let Err(e) = rt.block_on(push::run(args)) {
-                eprintln!("\n{} {}", red!("X Error in push:"), e);
-                std::process::exit(1);
+
+            if let Err(e) = result {
+                eprintln!();
+                report.exit("Error in push", &e);
             }
         }
         None => {
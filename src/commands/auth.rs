@@ -1,33 +1,18 @@
-use std::{env, thread::sleep, time::Duration, io::Write};
+use std::{io::Write, time::Duration};
 
 use open::that as open_browser;
-use reqwest::blocking::Client;
-use serde::Deserialize;
-
-use crate::constants::{AUTH_POLL_URL, AUTH_REQUEST_URL};
-use crate::errors::auth::AuthError;
-use crate::green;
-use crate::network::{build_client, verify_api_key};
-use crate::utils::get_config_path;
-
-// API is JavaScript so we need to camelcase
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct RequestResponse {
-    poll_token: String,
-    verify_url: String,
-}
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct PollResponse {
-    status: String,
-    api_key: Option<String>,
-}
+use clawdrop::client::{AuthStatus, RaccreativeClient};
+use clawdrop::errors::auth::AuthError;
+use clawdrop::green;
+use clawdrop::network::invalidate_games_cache;
+use clawdrop::utils::{get_api_key, get_config_path};
+
+pub async fn run(force: bool, headless: bool, key: Option<String>) -> Result<(), AuthError> {
+    let client = RaccreativeClient::new()?;
 
-pub fn run(force: bool, headless: bool, key: Option<String>) -> Result<(), AuthError> {
     if let Some(api_key) = key {
-        let valid = verify_api_key(&api_key)?;
+        let valid = client.verify_api_key(&api_key).await?;
 
         if !valid {
             return Err(AuthError::InvalidProvidedKey);
@@ -36,31 +21,33 @@ pub fn run(force: bool, headless: bool, key: Option<String>) -> Result<(), AuthE
         save_api_key(&api_key)?;
         return Ok(());
     }
-    
-    if !force {
-        if let Ok(existing) = env::var("CLAWDROP_API_KEY") {
-            let key_exists = !existing.trim().is_empty();
-            if key_exists && verify_api_key(&existing)? {
-                println!("You are already authorized.");
-                return Ok(());
-            }
-        }
-    }
 
-    let client = build_client()?;
+    if !force
+        && let Ok(existing) = get_api_key()
+        && client.verify_api_key(&existing).await?
+    {
+        println!("You are already authorized.");
+        return Ok(());
+    }
 
-    let auth_response = request_auth(&client)?;
+    let auth_response = client.request_auth().await?;
 
-    println!("Auth required. Open this url:\n{}", auth_response.verify_url);
+    println!(
+        "Auth required. Open this url:\n{}",
+        auth_response.verify_url
+    );
 
     if !headless {
         open_browser(&auth_response.verify_url)?;
     }
 
-    return poll_for_authorization(auth_response.poll_token, &client);
+    poll_for_authorization(&auth_response.poll_token, &client).await
 }
 
-fn poll_for_authorization(poll_token: String, client: &Client) -> Result<(), AuthError> {
+async fn poll_for_authorization(
+    poll_token: &str,
+    client: &RaccreativeClient,
+) -> Result<(), AuthError> {
     let start = std::time::Instant::now();
     let timeout = Duration::from_secs(240);
 
@@ -69,45 +56,23 @@ fn poll_for_authorization(poll_token: String, client: &Client) -> Result<(), Aut
             return Err(AuthError::RequestTimeout);
         }
 
-        sleep(Duration::from_secs(2));
+        tokio::time::sleep(Duration::from_secs(2)).await;
 
-    let poll_res: PollResponse = client
-        .get(AUTH_POLL_URL)
-        .query(&[("pollToken", &poll_token)])
-        .send()?
-        .json()?;
-
-        match poll_res.status.as_str() {
-            "pending" => {
+        match client.poll_auth(poll_token).await? {
+            AuthStatus::Pending => {
                 println!("Awaiting authorization...");
                 continue;
             }
-            "authorized" => {
-                if let Some(key) = poll_res.api_key {
-                    println!("Authorized correctly {}", green!("✓"));
-                    println!("Your API Key: {}", &key);
-                    save_api_key(&key)?;
-                    return Ok(());
-                } else {
-                    return Err(AuthError::MissingApiKeyInResponse);
-                }
+            AuthStatus::Authorized(key) => {
+                println!("Authorized correctly {}", green!("✓"));
+                println!("Your API Key: {}", &key);
+                save_api_key(&key)?;
+                return Ok(());
             }
-            "expired" => return Err(AuthError::RequestExpired),
-            "null" => return Err(AuthError::InvalidTokenResponse),
-            _ => return Err(AuthError::UnknownStatus(poll_res.status)),
         }
     }
 }
 
-fn request_auth(client: &Client) -> Result<RequestResponse, AuthError> {
-    let response: RequestResponse = client
-        .post(AUTH_REQUEST_URL)
-        .send()?
-        .json()?;
-
-    Ok(response)
-}
-
 fn save_api_key_to_file(key: &str) -> Result<(), AuthError> {
     let path = get_config_path().join(".api_key");
 
@@ -124,7 +89,8 @@ fn save_api_key_to_file(key: &str) -> Result<(), AuthError> {
 
 fn save_api_key(key: &str) -> Result<(), AuthError> {
     save_api_key_to_file(key)?;
-    unsafe { env::set_var("CLAWDROP_API_KEY", key) };
+    // The cached games belong to the previous key
+    invalidate_games_cache();
     println!("{}", green!("Authorization complete."));
     Ok(())
 }
@@ -0,0 +1,23 @@
+use std::io;
+
+use clap::CommandFactory;
+use clap_complete::{Shell, env::Shells, generate};
+
+use crate::cli::Cli;
+
+// By default we print the dynamic registration, which calls back into clawdrop and can complete game slugs
+pub fn run(shell: Shell, static_script: bool) -> Result<(), io::Error> {
+    let mut stdout = io::stdout();
+
+    if static_script {
+        generate(shell, &mut Cli::command(), "clawdrop", &mut stdout);
+        return Ok(());
+    }
+
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| io::Error::other(format!("Unsupported shell: {}", shell)))?;
+
+    completer.write_registration("COMPLETE", "clawdrop", "clawdrop", "clawdrop", &mut stdout)
+}
@@ -1,90 +1,495 @@
-use std::net::{SocketAddr, TcpStream};
-use std::time::Duration;
-use std::{fs, io, path::Path};
+use std::time::{Duration, Instant};
+use std::{env, fs, path::Path};
 
-use crate::errors::doctor::DoctorError;
-use crate::green;
-use crate::network::verify_api_key;
-use crate::utils::{get_api_key, get_target_game};
+use chrono::{DateTime, Utc};
+use tokio::net::{TcpStream, lookup_host};
+use tokio::time::timeout;
 
-// To use Doctor as a reliable pipeline, we must propagate errors for exit code 1
-pub fn run() -> Result<(), DoctorError> {
-    check_internet()?;
-    println!("Internet connection {}", green!("✓ OK"));
+use clawdrop::client::RaccreativeClient;
+use clawdrop::config::{load_config, load_project_config};
+use clawdrop::constants::VERIFY_API_KEY_URL;
+use clawdrop::errors::doctor::DoctorError;
+use clawdrop::network::network_settings;
+use clawdrop::pusher::check_s3_connection;
+use clawdrop::utils::{format_bytes, get_api_key, get_config_path, get_target_game};
+use clawdrop::{green, red};
 
-    println!("Checking for clawdrop authorization...");
-    let key = get_api_key()?;
-    println!("Api Key found: {}", mask_key(&key));
+// S3 rejects signatures more than 15 minutes off, warn well before that
+const CLOCK_SKEW_WARN: i64 = 60;
+const CLOCK_SKEW_FAIL: i64 = 5 * 60;
+const DISK_SPACE_WARN: u64 = 1024 * 1024 * 1024;
+const DISK_SPACE_FAIL: u64 = 100 * 1024 * 1024;
 
-    let valid = verify_api_key(&key)?;
-    if !valid {
-        return Err(DoctorError::InvalidApiKey);
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+// Result of one check, with what to do about it when it is not ok
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn print(&self) {
+        let mark = match self.status {
+            Status::Ok => green!("✓"),
+            Status::Warn => "\x1b[33m!\x1b[0m".to_string(),
+            Status::Fail => red!("X"),
+        };
+        println!("{} {}: {}", mark, self.name, self.detail);
+
+        if let Some(hint) = &self.hint {
+            println!("    → {}", hint);
+        }
     }
+}
+
+// Every check runs even after a failure, the exit code is 1 if any of them failed
+pub async fn run() -> Result<(), DoctorError> {
+    let host = reqwest::Url::parse(VERIFY_API_KEY_URL)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let proxy = detect_proxy();
+    let client = RaccreativeClient::new()?;
+
+    let mut checks = vec![
+        check_config_dir(),
+        check_config_files(),
+        check_api_key_file(),
+        check_proxy(&proxy),
+        check_api_host(&host, proxy.is_some()).await,
+    ];
+    checks.extend(check_tls_and_clock(&client, &host).await);
+    checks.push(check_s3(proxy.is_some()).await);
+    checks.push(check_api_key(&client).await);
+    checks.push(check_disk_space());
+    checks.push(check_write_permissions("."));
+    checks.push(check_target_game());
 
-    println!("API key valid {}", green!("✓ OK"));
+    let mut failed = 0;
+    let mut warnings = 0;
+    for check in &checks {
+        check.print();
+        match check.status {
+            Status::Fail => failed += 1,
+            Status::Warn => warnings += 1,
+            Status::Ok => {}
+        }
+    }
 
-    check_write_permissions(".")?;
-    println!("Permissions {}", green!("✓ OK"));
+    println!(
+        "\n{} checks: {} ok, {} warnings, {} failed",
+        checks.len(),
+        checks.len() - failed - warnings,
+        warnings,
+        failed
+    );
 
-    check_target_game();
+    if failed > 0 {
+        return Err(DoctorError::ChecksFailed(failed));
+    }
 
     Ok(())
 }
 
-fn check_target_game() {
-    println!("Checking if a target game is configured...");
+fn check_config_dir() -> Check {
+    let name = "Config directory";
+    let dir = get_config_path();
 
-    match get_target_game() {
-        Ok(Some(game)) => {
-            println!("Target game found: {}", green!(&game.title));
+    if !dir.exists() {
+        return Check::warn(
+            name,
+            format!("{} does not exist yet", dir.display()),
+            "run clawdrop auth, it creates it",
+        );
+    }
+
+    let test_path = dir.join(".clawdrop_write_test");
+    match fs::write(&test_path, b"test").and_then(|_| fs::remove_file(&test_path)) {
+        Ok(()) => Check::ok(name, dir.display().to_string()),
+        Err(e) => Check::fail(
+            name,
+            format!("{} is not writable ({})", dir.display(), e),
+            "fix the owner or permissions of that directory",
+        ),
+    }
+}
+
+fn check_config_files() -> Check {
+    let name = "Config files";
+    let mut problems = Vec::new();
+
+    if let Err(e) = load_config() {
+        problems.push(format!("config.json: {}", e));
+    }
+    if let Err(e) = load_project_config() {
+        problems.push(format!("clawdrop.json: {}", e));
+    }
+
+    if problems.is_empty() {
+        Check::ok(name, "config.json and clawdrop.json are valid or absent")
+    } else {
+        Check::fail(name, problems.join("; "), "fix the JSON or delete the file")
+    }
+}
+
+// The key grants push access, other users on the machine should not read it
+fn check_api_key_file() -> Check {
+    let name = "API key file";
+    let path = get_config_path().join(".api_key");
+
+    let Ok(metadata) = fs::metadata(&path) else {
+        return Check::ok(name, "not stored on disk");
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Check::warn(
+                name,
+                format!("{} is readable by other users ({:o})", path.display(), mode),
+                format!("chmod 600 {}", path.display()),
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+
+    Check::ok(name, path.display().to_string())
+}
+
+// Proxy every client goes through (flag, config.json or environment), with credentials hidden
+fn detect_proxy() -> Option<String> {
+    let proxy = network_settings().proxy.as_ref()?;
+    Some(match reqwest::Url::parse(proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("****"));
+            url.to_string()
+        }
+        _ => proxy.clone(),
+    })
+}
+
+fn check_proxy(proxy: &Option<String>) -> Check {
+    let name = "Proxy";
+    let settings = network_settings();
+
+    let mut detail = match proxy {
+        Some(url) => url.clone(),
+        None => "none, direct connection".to_string(),
+    };
+    if proxy.is_some()
+        && let Some(no_proxy) = &settings.no_proxy
+    {
+        detail.push_str(&format!(" (not for {})", no_proxy));
+    }
+    if settings.ca_cert_pem.is_some() {
+        detail.push_str(", extra CA bundle loaded");
+    }
+
+    Check::ok(name, detail)
+}
+
+async fn check_api_host(host: &str, behind_proxy: bool) -> Check {
+    let name = "API host";
+    let start = Instant::now();
+
+    let addr = match lookup_host((host, 443)).await.map(|mut addrs| addrs.next()) {
+        Ok(Some(addr)) => addr,
+        Ok(None) | Err(_) if behind_proxy => {
+            return Check::warn(
+                name,
+                format!("{} does not resolve locally", host),
+                "fine if the proxy resolves it, otherwise check your DNS",
+            );
         }
         Ok(None) => {
-            println!("No target game configured (you can use 'clawdrop set' to define one).");
+            return Check::fail(name, format!("{} has no address", host), "check your DNS");
         }
-        Err(_) => {
-            println!("Target file exists but could not be read or parsed.");
+        Err(e) => {
+            return Check::fail(
+                name,
+                format!("could not resolve {} ({})", host, e),
+                "check your internet connection and DNS",
+            );
         }
+    };
+
+    let connected = timeout(Duration::from_secs(5), TcpStream::connect(addr))
+        .await
+        .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()));
+    match connected {
+        Ok(_) => Check::ok(
+            name,
+            format!("{} reachable ({} ms)", host, start.elapsed().as_millis()),
+        ),
+        Err(e) if behind_proxy => Check::warn(
+            name,
+            format!("no direct connection to {}:443 ({})", host, e),
+            "expected behind a proxy, the HTTPS check below goes through it",
+        ),
+        Err(e) => Check::fail(
+            name,
+            format!("could not connect to {}:443 ({})", host, e),
+            "a firewall may block it, allow outgoing HTTPS to that host or use --proxy",
+        ),
     }
 }
 
-fn check_internet() -> Result<(), DoctorError> {
-    println!("Checking internet connection...");
+// One HTTPS request checks the handshake and gives the server clock from its Date header
+async fn check_tls_and_clock(client: &RaccreativeClient, host: &str) -> Vec<Check> {
+    let tls = "TLS handshake";
+    let clock = "Clock";
 
-    let addr: SocketAddr = "8.8.8.8:53"
-        .parse()
-        .map_err(|e| DoctorError::InternetUnavailable {
-            message: "Could not parse address".into(),
-            source: Some(Box::new(e)),
-        })?; // Google DNS
+    let response = match client
+        .http()
+        .head(format!("https://{}/", host))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            let message = error_chain(&e);
+            let hint = if message.to_lowercase().contains("certificate") {
+                "the certificate is not trusted, behind a TLS-intercepting proxy pass its CA with --ca-cert <pem> or caCert in config.json"
+            } else if e.is_timeout() {
+                "the request timed out, check the connection or proxy"
+            } else {
+                "check the connection, proxy settings and firewall"
+            };
 
-    TcpStream::connect_timeout(&addr, Duration::from_secs(2)).map_err(|e| {
-        DoctorError::InternetUnavailable {
-            message: "Could not connect to 8.8.8.8:53 (Google DNS)".into(),
-            source: Some(Box::new(e)),
+            return vec![
+                Check::fail(tls, message, hint),
+                Check::warn(
+                    clock,
+                    "not checked, the server could not be reached",
+                    "make sure the system clock is synchronized (NTP)",
+                ),
+            ];
         }
-    })?;
+    };
 
-    Ok(())
+    let tls_check = Check::ok(tls, format!("https://{} ({:?})", host, response.version()));
+
+    let server_time = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+
+    let Some(server_time) = server_time else {
+        return vec![
+            tls_check,
+            Check::warn(
+                clock,
+                "the server sent no Date header",
+                "make sure the system clock is synchronized (NTP)",
+            ),
+        ];
+    };
+
+    let skew = (Utc::now() - server_time.with_timezone(&Utc)).num_seconds();
+    let detail = format!(
+        "{} s {} the server",
+        skew.abs(),
+        if skew >= 0 { "ahead of" } else { "behind" }
+    );
+    let hint = "enable automatic time sync (NTP), a skewed clock breaks the S3 upload signatures";
+
+    let clock_check = match skew.abs() {
+        s if s >= CLOCK_SKEW_FAIL => Check::fail(clock, detail, hint),
+        s if s >= CLOCK_SKEW_WARN => Check::warn(clock, detail, hint),
+        _ => Check::ok(clock, detail),
+    };
+
+    vec![tls_check, clock_check]
+}
+
+// Builds go straight to S3 through its own HTTP client, which can fail where the API works
+async fn check_s3(behind_proxy: bool) -> Check {
+    let name = "S3 connection";
+    let start = Instant::now();
+
+    match check_s3_connection().await {
+        Ok(()) => Check::ok(
+            name,
+            format!(
+                "reachable{} ({} ms)",
+                if behind_proxy {
+                    " through the proxy"
+                } else {
+                    ""
+                },
+                start.elapsed().as_millis()
+            ),
+        ),
+        Err(e) => {
+            let message = e.to_string();
+            let hint = if message.to_lowercase().contains("certificate") {
+                "the certificate is not trusted, behind a TLS-intercepting proxy pass its CA with --ca-cert <pem> or caCert in config.json"
+            } else {
+                "uploads need outgoing HTTPS to *.amazonaws.com, allow it in the firewall or use --proxy"
+            };
+            Check::fail(name, message, hint)
+        }
+    }
+}
+
+async fn check_api_key(client: &RaccreativeClient) -> Check {
+    let name = "API key";
+
+    let Ok(key) = get_api_key() else {
+        return Check::fail(name, "not found", "run clawdrop auth");
+    };
+
+    match client.verify_api_key(&key).await {
+        Ok(true) => Check::ok(name, format!("{} is valid", mask_key(&key))),
+        Ok(false) => Check::fail(
+            name,
+            format!("{} is invalid or expired", mask_key(&key)),
+            "run clawdrop auth --force for a new one",
+        ),
+        Err(e) => Check::warn(
+            name,
+            format!("{} could not be verified ({})", mask_key(&key), e),
+            "see the connection checks above",
+        ),
+    }
 }
 
-fn check_write_permissions<P: AsRef<Path>>(path: P) -> io::Result<()> {
-    println!("Checking current directory permissions...");
+// Pushing hashes the build in place, but temporary files and the config still need room
+fn check_disk_space() -> Check {
+    let name = "Disk space";
+    let mut lowest: Option<(String, u64)> = None;
+
+    for dir in [Path::new(".").to_path_buf(), env::temp_dir()] {
+        match fs4::available_space(&dir) {
+            Ok(space) if lowest.as_ref().is_none_or(|(_, low)| space < *low) => {
+                lowest = Some((dir.display().to_string(), space));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return Check::warn(
+                    name,
+                    format!("could not read free space of {} ({})", dir.display(), e),
+                    "make sure there is free space left",
+                );
+            }
+        }
+    }
+
+    let Some((dir, space)) = lowest else {
+        return Check::ok(name, "unknown");
+    };
+    let detail = format!("{} free in {}", format_bytes(space), dir);
+
+    match space {
+        s if s < DISK_SPACE_FAIL => Check::fail(name, detail, "free some disk space"),
+        s if s < DISK_SPACE_WARN => {
+            Check::warn(name, detail, "less than 1 GB left, free some space")
+        }
+        _ => Check::ok(name, detail),
+    }
+}
+
+fn check_write_permissions<P: AsRef<Path>>(path: P) -> Check {
+    let name = "Current directory";
 
     let test_path = path.as_ref().join(".clawdrop_write_test");
-    fs::write(&test_path, b"test")?;
-    fs::remove_file(test_path)?;
-    Ok(())
+    match fs::write(&test_path, b"test").and_then(|_| fs::remove_file(&test_path)) {
+        Ok(()) => Check::ok(name, "writable"),
+        Err(e) => Check::fail(
+            name,
+            format!("not writable ({})", e),
+            "run clawdrop from a directory you own",
+        ),
+    }
 }
 
+fn check_target_game() -> Check {
+    let name = "Target game";
+
+    match get_target_game() {
+        Ok(Some(game)) => Check::ok(name, format!("{} (ID {})", game.title, game.id)),
+        Ok(None) => Check::ok(name, "none, use clawdrop set <id> to define one"),
+        Err(e) => Check::fail(
+            name,
+            format!("target.json could not be read or parsed ({})", e),
+            "run clawdrop set <id> again",
+        ),
+    }
+}
+
+// reqwest hides the interesting part (certificate, DNS, refused) in the source chain
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(&format!(": {}", e));
+        source = e.source();
+    }
+    message
+}
+
+// Keys are ASCII today, counting chars keeps anything else from splitting a character
 fn mask_key(key: &str) -> String {
-    if key.len() <= 8 {
-        "*".repeat(key.len())
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 8 {
+        "*".repeat(chars.len())
     } else {
         format!(
             "{}{}{}",
-            &key[..4],
-            "*".repeat(key.len().saturating_sub(8)),
-            &key[key.len().saturating_sub(4)..]
+            chars[..4].iter().collect::<String>(),
+            "*".repeat(chars.len() - 8),
+            chars[chars.len() - 4..].iter().collect::<String>()
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_masked_by_character() {
+        assert_eq!(mask_key("abcd1234"), "********");
+        assert_eq!(mask_key("abcd12345678wxyz"), "abcd********wxyz");
+        assert_eq!(mask_key("ñañañaña-clave-ü"), "ñaña********ve-ü");
+    }
+}
@@ -0,0 +1,53 @@
+use clawdrop::{
+    errors::index::IndexError,
+    file_index::{generate_fileindex, read_fileindex, verify_fileindex, write_fileindex},
+    green,
+};
+
+use crate::ui::CliUi;
+
+pub fn create(path: String, output: String, ignore: Vec<String>) -> Result<(), IndexError> {
+    let ui = CliUi::new();
+    let spinner = ui.start_spinner("Generating fileindex snapshot");
+
+    let index = generate_fileindex(&path, &ignore)?;
+    write_fileindex(&output, &index)?;
+
+    spinner.stop();
+
+    let total_bytes: u64 = index.files.iter().map(|f| f.size).sum();
+    println!(
+        "Snapshot with {} files ({} bytes) written to {} {}",
+        index.files.len(),
+        total_bytes,
+        output,
+        green!("✓")
+    );
+
+    Ok(())
+}
+
+pub fn verify(path: String, index: String, ignore: Vec<String>) -> Result<(), IndexError> {
+    let ui = CliUi::new();
+    let spinner = ui.start_spinner("Verifying build against fileindex snapshot");
+
+    let snapshot = read_fileindex(&index)?;
+    let mismatches = verify_fileindex(&path, &snapshot, &ignore)?;
+
+    spinner.stop();
+
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            println!("  {}", mismatch);
+        }
+        return Err(IndexError::Mismatch(mismatches));
+    }
+
+    println!(
+        "{} files match the snapshot {}",
+        snapshot.files.len(),
+        green!("✓ OK")
+    );
+
+    Ok(())
+}
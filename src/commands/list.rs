@@ -1,45 +1,232 @@
+use chrono::{DateTime, Local};
+use clawdrop::{
+    client::{Game, RaccreativeClient},
+    errors::list::ListError,
+    network::load_games_list,
+    utils::{format_bytes, get_api_key, get_target_game},
+};
 
-use crate::{errors::list::ListError, network::{get_developed_games_list}, utils::get_api_key};
+// Longer cells are cut with an ellipsis so rows never wrap
+pub(crate) const MAX_TITLE_WIDTH: usize = 32;
+pub(crate) const MAX_CELL_WIDTH: usize = 24;
+pub const PLATFORMS: [&str; 4] = ["windows", "linux", "mac", "html"];
 
-pub fn run() -> Result<(), ListError> {
-    let api_key = get_api_key()?;
+pub async fn run(
+    filter: Option<String>,
+    sort: Option<String>,
+    os: Option<String>,
+    columns: Vec<String>,
+    refresh: bool,
+) -> Result<(), ListError> {
+    let client = RaccreativeClient::new()?.with_api_key(get_api_key()?);
 
-    let response = get_developed_games_list(api_key)?;
+    let list = load_games_list(&client, refresh).await?;
+    if let Some(warning) = list.offline_warning() {
+        eprintln!("Warning: {}", warning);
+    }
+    let mut games = list.response.games;
+
+    if let Some(filter) = &filter {
+        let filter = filter.to_lowercase();
+        games.retain(|game| {
+            game.title.to_lowercase().contains(&filter)
+                || game
+                    .url_identifier
+                    .as_ref()
+                    .is_some_and(|slug| slug.to_lowercase().contains(&filter))
+                || game.id.to_string().contains(&filter)
+        });
+    }
+
+    if let Some(os) = &os {
+        games.retain(|game| game.version_for(os).is_some());
+    }
 
-    if response.games.is_empty() {
+    match sort.as_deref() {
+        Some("title") => games.sort_by_key(|game| game.title.to_lowercase()),
+        Some("id") => games.sort_by_key(|game| game.id),
+        // Most recent first, games never updated at the end
+        Some("updated") => games
+            .sort_by_key(|game| std::cmp::Reverse(game.updated_at.as_deref().and_then(parse_time))),
+        _ => {}
+    }
+
+    if games.is_empty() {
         println!("No games found.");
         return Ok(());
     }
 
-    // We create a table to list our games nicely
+    let rows: Vec<Vec<String>> = games
+        .iter()
+        .map(|game| columns.iter().map(|column| cell(game, column)).collect())
+        .collect();
+
+    let headers: Vec<String> = columns.iter().map(|c| header(c).to_string()).collect();
+    let limits: Vec<usize> = columns
+        .iter()
+        .map(|column| {
+            if column == "title" {
+                MAX_TITLE_WIDTH
+            } else {
+                MAX_CELL_WIDTH
+            }
+        })
+        .collect();
+    print_table(&headers, &rows, &limits);
+
+    Ok(())
+}
+
+// Game by id or slug, the target when none is given
+pub async fn info(id: Option<String>, refresh: bool) -> Result<(), ListError> {
+    let client = RaccreativeClient::new()?.with_api_key(get_api_key()?);
+
+    let id = match id {
+        Some(id) => id,
+        None => get_target_game()?
+            .map(|game| game.id.to_string())
+            .ok_or(ListError::NoIdSpecified)?,
+    };
+
+    let game = load_games_list(&client, refresh)
+        .await?
+        .response
+        .find(&id)
+        .ok_or_else(|| ListError::GameNotFound(id.clone()))?;
+    let details = client.game_details(game.id).await?;
+
+    println!("{} (ID {})", details.title, details.id);
+    println!("URL:        {}", details.url.as_deref().unwrap_or("-"));
+    println!(
+        "Slug:       {}",
+        game.url_identifier.as_deref().unwrap_or("-")
+    );
     println!(
-        "{:<10} | {:<30} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15}", 
-        "ID", 
-        "Title", 
-        "URL Identifier",
-        "Windows Version", 
-        "Linux Version", 
-        "Mac Version", 
-        "HTML Version"
+        "Last push:  {}",
+        details
+            .last_pushed_at
+            .as_deref()
+            .map(format_time)
+            .unwrap_or_else(|| "never".to_string())
     );
+    println!();
 
     println!(
-        "{:-<10}-+-{:-<30}-+-{:-<15}-+-{:-<15}-+-{:-<15}-+-{:-<15}-+-{:-<15}", 
-        "", "", "", "", "", "", ""
+        "{:<10} | {:<15} | {:<10} | {:<16}",
+        "Platform", "Version", "Size", "Pushed"
     );
+    println!("{:-<10}-+-{:-<15}-+-{:-<10}-+-{:-<16}", "", "", "", "");
+
+    for os in PLATFORMS {
+        let build = details.builds.iter().find(|build| build.os == os);
+        let version = build
+            .map(|build| build.version.as_str())
+            .or(game.version_for(os).map(|v| v.as_str()))
+            .unwrap_or("-");
+        let size = build
+            .and_then(|build| build.size)
+            .map(format_bytes)
+            .unwrap_or_else(|| "-".to_string());
+        let pushed = build
+            .and_then(|build| build.pushed_at.as_deref())
+            .map(format_time)
+            .unwrap_or_else(|| "-".to_string());
 
-    for game in response.games {
         println!(
-            "{:<10} | {:<30} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15}", 
-            game.id, 
-            game.title,
-            game.url_identifier.unwrap_or("null".to_string()),
-            game.windows_version.unwrap_or("null".to_string()),
-            game.linux_version.unwrap_or("null".to_string()),
-            game.mac_version.unwrap_or("null".to_string()),
-            game.html_version.unwrap_or("null".to_string()),
+            "{:<10} | {:<15} | {:<10} | {:<16}",
+            os,
+            truncate(version, 15),
+            size,
+            pushed
         );
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn header(column: &str) -> &'static str {
+    match column {
+        "id" => "ID",
+        "title" => "Title",
+        "slug" => "URL Identifier",
+        "windows" => "Windows",
+        "linux" => "Linux",
+        "mac" => "Mac",
+        "html" => "HTML",
+        "updated" => "Updated",
+        _ => "",
+    }
+}
+
+fn cell(game: &Game, column: &str) -> String {
+    let value = match column {
+        "id" => Some(game.id.to_string()),
+        "title" => Some(game.title.clone()),
+        "slug" => game.url_identifier.clone(),
+        "updated" => game.updated_at.as_deref().map(format_time),
+        os => game.version_for(os).cloned(),
+    };
+
+    value.unwrap_or_else(|| "-".to_string())
+}
+
+// Each column is as wide as its content, up to its limit
+pub(crate) fn print_table(headers: &[String], rows: &[Vec<String>], limits: &[usize]) {
+    let widths: Vec<usize> = limits
+        .iter()
+        .enumerate()
+        .map(|(i, limit)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([headers[i].chars().count()])
+                .max()
+                .unwrap_or(0)
+                .min(*limit)
+        })
+        .collect();
+
+    print_row(headers, &widths);
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("-+-")
+    );
+
+    for row in rows {
+        print_row(row, &widths);
+    }
+}
+
+fn print_row(cells: &[String], widths: &[usize]) {
+    let line: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", truncate(cell, *width), width = width))
+        .collect();
+    println!("{}", line.join(" | ").trim_end());
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    format!("{}…", cut)
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+// API timestamps in local time, left as they are if they are not RFC 3339
+pub fn format_time(time: &str) -> String {
+    parse_time(time)
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| time.to_string())
+}
@@ -0,0 +1,10 @@
+use std::io;
+
+use clap::CommandFactory;
+use clap_mangen::Man;
+
+use crate::cli::Cli;
+
+pub fn run() -> Result<(), io::Error> {
+    Man::new(Cli::command()).render(&mut io::stdout())
+}
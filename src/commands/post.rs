@@ -1,86 +1,532 @@
-use std::{fs, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
+use chrono::{DateTime, Local, Utc};
 use mime_guess::MimeGuess;
-use reqwest::blocking::multipart;
 
-use crate::{
-    constants::GAME_POST_URL,
+use clawdrop::{
+    changelog::{self, Commit},
+    client::{NewPost, Post, PostImage, PostUpdate, RaccreativeClient},
+    cover::prepare_cover,
+    devlog::Devlog,
     errors::post::PostError,
     green,
-    network::build_client,
+    pusher::PushSummary,
+    red,
+    schedule::{ScheduledPost, read_queue, write_queue},
     utils::{get_api_key, get_target_game},
 };
 
-pub fn run(
-    id: Option<u64>,
-    title: String,
-    body: String,
-    cover: Option<String>,
-    slug: Option<String>,
+use crate::{
+    cli::{CoverArgs, PostCreateArgs, PostEditArgs},
+    commands::list::{MAX_CELL_WIDTH, MAX_TITLE_WIDTH, print_table},
+};
+
+// Changelog for push --announce, resolved before the push so a bad range fails it early
+pub struct Announcement {
+    range: String,
+    commits: Vec<Commit>,
+}
+
+pub fn prepare_announcement(
+    range: String,
+    commit_types: &[String],
+) -> Result<Announcement, PostError> {
+    // An empty range means since the previous tag
+    let range = if range.is_empty() {
+        changelog::default_range()?
+    } else {
+        range
+    };
+    let commits = changelog::commits_in_range(&range, commit_types)?;
+
+    Ok(Announcement { range, commits })
+}
+
+pub async fn run(
+    args: PostCreateArgs,
+    announcement: Option<(&PushSummary, Vec<Commit>)>,
 ) -> Result<(), PostError> {
-    let game_id = match id {
-        Some(id) => id,
-        None => match get_target_game() {
-            Ok(Some(game)) => game.id,
-            Ok(None) => return Err(PostError::NoIdSpecified),
-            Err(e) => return Err(PostError::Common(e.into())),
-        },
+    let (push, commits) = announcement.unzip();
+
+    let game_id = resolve_game_id(args.id)?;
+
+    // Everything is read and validated before the first request
+    let devlog = args
+        .from
+        .as_deref()
+        .map(|path| Devlog::load(Path::new(path)))
+        .transpose()?;
+    let front_matter = devlog.as_ref().map(|devlog| &devlog.front_matter);
+
+    let title = args
+        .title
+        .or_else(|| front_matter.and_then(|f| f.title.clone()))
+        .or_else(|| {
+            args.changelog
+                .as_deref()
+                .map(|range| changelog_title(range, push))
+        })
+        .ok_or(PostError::MissingTitle)?;
+    let slug = args
+        .slug
+        .or_else(|| front_matter.and_then(|f| f.slug.clone()));
+    let tags = front_matter.map(|f| f.tags.clone()).unwrap_or_default();
+    validate_fields(Some(&title), slug.as_deref(), args.publish_at)?;
+
+    let cover_path = args
+        .cover
+        .map(PathBuf::from)
+        .or_else(|| devlog.as_ref().and_then(|devlog| devlog.cover.clone()));
+    let cover = cover_path
+        .as_deref()
+        .map(|path| read_cover(path, &args.cover_options))
+        .transpose()?;
+
+    // --body, --from and --changelog are exclusive, clap requires one of them
+    let body = match &args.changelog {
+        Some(range) => {
+            let commits = match commits {
+                Some(commits) => commits,
+                None => changelog::commits_in_range(range, &args.commit_types)?,
+            };
+            Some(changelog::render(&commits, push))
+        }
+        None => args.body.map(resolve_body).transpose()?,
+    };
+    let images = match &devlog {
+        Some(devlog) => devlog
+            .images
+            .iter()
+            .map(|path| Ok((path.clone(), read_image(path)?)))
+            .collect::<Result<Vec<_>, PostError>>()?,
+        None => Vec::new(),
+    };
+
+    let client = build_client()?;
+
+    let final_body = match &devlog {
+        Some(devlog) => {
+            let mut urls = HashMap::new();
+            for (path, image) in images {
+                let url = client.upload_post_image(game_id, image).await?;
+                println!("{} Uploaded {}", green!("✓"), path.display());
+                urls.insert(path, url);
+            }
+            devlog.body_with_urls(&urls)
+        }
+        None => body.unwrap_or_default(),
     };
 
-    let final_body = resolve_body(body)?;
+    let post = client
+        .create_post(NewPost {
+            game_id,
+            title,
+            body: final_body,
+            slug,
+            cover,
+            tags,
+            draft: args.draft,
+            publish_at: args.publish_at,
+        })
+        .await?;
+
+    if let Some(publish_at) = args.publish_at {
+        // Servers without scheduling keep it as a plain draft, we publish it on the next flush after the date
+        if post.publish_at.is_none() {
+            let mut queue = read_queue()?;
+            queue.push(ScheduledPost {
+                post_id: post.id,
+                game_id,
+                title: post.title.clone(),
+                publish_at,
+            });
+            write_queue(&queue)?;
 
-    let api_key = get_api_key()?;
+            println!("Post saved as draft and queued locally. {}", green!("✓ OK"));
+            print_post(&post);
+            println!(
+                "Run clawdrop post flush after {} to publish it (a cron job or scheduled task works well)",
+                publish_at.with_timezone(&Local).format("%Y-%m-%d %H:%M %Z")
+            );
+        } else {
+            println!(
+                "Post scheduled for {}. {}",
+                publish_at.with_timezone(&Local).format("%Y-%m-%d %H:%M %Z"),
+                green!("✓ OK")
+            );
+            print_post(&post);
+        }
+    } else if post.draft {
+        println!("Draft saved. {}", green!("✓ OK"));
+        print_post(&post);
+        println!("Publish it with: clawdrop post publish {}", post.id);
+    } else {
+        println!("Post created. {}", green!("✓ OK"));
+        print_post(&post);
+    }
+
+    Ok(())
+}
+
+// Changelog post for a finished push
+pub async fn announce(summary: &PushSummary, announcement: Announcement) -> Result<(), PostError> {
+    if summary.uploaded_files == 0 && summary.deleted_files == 0 {
+        println!("Nothing changed in the build, no announcement posted");
+        return Ok(());
+    }
+
+    let args = PostCreateArgs {
+        id: Some(summary.game_id),
+        title: None,
+        body: None,
+        from: None,
+        changelog: Some(announcement.range),
+        commit_types: Vec::new(),
+        cover: None,
+        cover_options: CoverArgs::default(),
+        slug: None,
+        draft: false,
+        publish_at: None,
+    };
+
+    run(args, Some((summary, announcement.commits))).await
+}
+
+pub async fn list(id: Option<u64>) -> Result<(), PostError> {
+    let game_id = resolve_game_id(id)?;
+    let client = build_client()?;
+
+    let posts = client.list_posts(game_id).await?;
+    let queue = read_queue()?;
+
+    if posts.is_empty() {
+        println!("No posts found.");
+        return Ok(());
+    }
+
+    let headers = ["ID", "Title", "Slug", "Status", "URL"].map(String::from);
+    let rows: Vec<Vec<String>> = posts
+        .iter()
+        .map(|post| {
+            vec![
+                post.id.to_string(),
+                post.title.clone(),
+                post.slug.as_deref().unwrap_or("null").to_string(),
+                status(post, &queue).to_string(),
+                post.url.as_deref().unwrap_or("null").to_string(),
+            ]
+        })
+        .collect();
+
+    // URLs are left whole, a cut one cannot be opened
+    let limits = [
+        MAX_CELL_WIDTH,
+        MAX_TITLE_WIDTH,
+        MAX_CELL_WIDTH,
+        MAX_CELL_WIDTH,
+        usize::MAX,
+    ];
+    print_table(&headers, &rows, &limits);
+
+    Ok(())
+}
+
+pub async fn edit(args: PostEditArgs) -> Result<(), PostError> {
+    let game_id = resolve_game_id(args.id)?;
+    validate_fields(args.title.as_deref(), args.slug.as_deref(), None)?;
+    let update = PostUpdate {
+        title: args.title,
+        body: args.body.map(resolve_body).transpose()?,
+        slug: args.slug,
+        cover: args
+            .cover
+            .map(|cover| read_cover(Path::new(&cover), &args.cover_options))
+            .transpose()?,
+    };
+
+    let client = build_client()?;
+    let existing = find_post(&client, game_id, &args.post).await?;
+    let updated = client.update_post(existing.id, update).await?;
+
+    println!("Post updated. {}", green!("✓ OK"));
+    print_post(&updated);
+
+    Ok(())
+}
+
+pub async fn delete(post: String, id: Option<u64>, yes: bool) -> Result<(), PostError> {
+    let game_id = resolve_game_id(id)?;
     let client = build_client()?;
+    let existing = find_post(&client, game_id, &post).await?;
+
+    if !yes {
+        print!(
+            "Delete post '{}' ({})? (y/n): ",
+            existing.title,
+            existing.slug.as_deref().unwrap_or("no slug")
+        );
+        io::stdout().flush()?;
 
-    let mut form = multipart::Form::new()
-        .text("gameId", game_id.to_string())
-        .text("title", title)
-        .text("body", final_body);
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim().to_lowercase();
 
-    if let Some(s) = slug {
-        form = form.text("slug", s);
+        if input != "y" && input != "yes" {
+            println!("Delete cancelled");
+            return Ok(());
+        }
     }
 
-    if let Some(cover_path) = &cover {
-        let bytes = fs::read(cover_path)?;
-        let mime_type = MimeGuess::from_path(cover_path).first_or_octet_stream();
+    client.delete_post(existing.id).await?;
+    unqueue(existing.id)?;
+    println!("Post {} deleted. {}", existing.id, green!("✓ OK"));
+
+    Ok(())
+}
 
-        let part = multipart::Part::bytes(bytes)
-            .file_name("cover.jpg")
-            .mime_str(mime_type.essence_str())?;
+pub async fn publish(post: String, id: Option<u64>) -> Result<(), PostError> {
+    let game_id = resolve_game_id(id)?;
+    let client = build_client()?;
+    let existing = find_post(&client, game_id, &post).await?;
 
-        form = form.part("cover", part);
+    if !existing.draft {
+        println!("Post is already published.");
+        print_post(&existing);
+        return Ok(());
     }
 
-    let response = match client
-        .post(GAME_POST_URL)
-        .header("x-api-key", api_key)
-        .multipart(form)
-        .send()
-    {
-        Ok(r) => match r.status() {
-            reqwest::StatusCode::FORBIDDEN => return Err(PostError::UnauthorizedToPost),
-            status if !status.is_success() => {
-                let text = r.text().unwrap_or_else(|_| "<no body>".into());
-                return Err(PostError::ServerError {
-                    code: status.as_u16(),
-                    message: text,
-                });
+    let published = client.publish_post(existing.id).await?;
+    unqueue(existing.id)?;
+
+    println!("Post published. {}", green!("✓ OK"));
+    print_post(&published);
+
+    Ok(())
+}
+
+// Publishes the queued posts whose date has passed, failed ones stay queued for the next flush
+pub async fn flush() -> Result<(), PostError> {
+    let queue = read_queue()?;
+    if queue.is_empty() {
+        println!("No scheduled posts in the local queue.");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let (due, mut remaining): (Vec<_>, Vec<_>) =
+        queue.into_iter().partition(|entry| entry.publish_at <= now);
+
+    let mut failed = 0;
+    if !due.is_empty() {
+        let client = build_client()?;
+
+        for entry in due {
+            match client.publish_post(entry.post_id).await {
+                Ok(post) => {
+                    println!("{} Published '{}'", green!("✓"), post.title);
+                    if let Some(url) = &post.url {
+                        println!("  URL: {}", url);
+                    }
+                }
+                Err(PostError::PostNotFound(_)) => {
+                    println!(
+                        "Post '{}' ({}) no longer exists, removed from the queue",
+                        entry.title, entry.post_id
+                    );
+                }
+                Err(e) => {
+                    eprintln!("{} '{}': {}", red!("X Failed to publish"), entry.title, e);
+                    remaining.push(entry);
+                    failed += 1;
+                }
             }
-            _ => r,
+        }
+    }
+
+    remaining.sort_by_key(|entry| entry.publish_at);
+    write_queue(&remaining)?;
+
+    for entry in remaining.iter().filter(|entry| entry.publish_at > now) {
+        println!(
+            "Pending: '{}' at {}",
+            entry.title,
+            entry
+                .publish_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M %Z")
+        );
+    }
+
+    if failed > 0 {
+        return Err(PostError::FlushFailed(failed));
+    }
+
+    Ok(())
+}
+
+fn unqueue(post_id: u64) -> Result<(), PostError> {
+    let mut queue = read_queue()?;
+    let before = queue.len();
+    queue.retain(|entry| entry.post_id != post_id);
+
+    if queue.len() != before {
+        write_queue(&queue)?;
+    }
+
+    Ok(())
+}
+
+fn build_client() -> Result<RaccreativeClient, PostError> {
+    Ok(RaccreativeClient::new()?.with_api_key(get_api_key()?))
+}
+
+fn resolve_game_id(id: Option<u64>) -> Result<u64, PostError> {
+    match id {
+        Some(id) => Ok(id),
+        None => match get_target_game() {
+            Ok(Some(game)) => Ok(game.id),
+            Ok(None) => Err(PostError::NoIdSpecified),
+            Err(e) => Err(PostError::Common(e)),
         },
-        Err(e) => return Err(e.into()),
+    }
+}
+
+// Posts are addressed by slug or numeric id, slugs win so a numeric slug still works
+async fn find_post(
+    client: &RaccreativeClient,
+    game_id: u64,
+    post: &str,
+) -> Result<Post, PostError> {
+    let posts = client.list_posts(game_id).await?;
+
+    let by_slug = posts.iter().position(|p| p.slug.as_deref() == Some(post));
+    let by_id = || {
+        let id = post.parse::<u64>().ok()?;
+        posts.iter().position(|p| p.id == id)
     };
 
-    println!(
-        "Post created. Code: {} {}",
-        response.status(),
-        green!("✓ OK")
-    );
+    match by_slug.or_else(by_id) {
+        Some(index) => Ok(posts.into_iter().nth(index).unwrap()),
+        None => Err(PostError::PostNotFound(post.to_string())),
+    }
+}
+
+fn read_cover(path: &Path, options: &CoverArgs) -> Result<PostImage, PostError> {
+    let prepared = prepare_cover(path, options.cover_fit, options.cover_format)?;
+
+    if !prepared.notes.is_empty() {
+        println!("Cover {}: {}", path.display(), prepared.notes.join(", "));
+    }
+
+    Ok(prepared.image)
+}
+
+// Title, slug and date checks the server would otherwise only reject after the images are uploaded
+fn validate_fields(
+    title: Option<&str>,
+    slug: Option<&str>,
+    publish_at: Option<DateTime<Utc>>,
+) -> Result<(), PostError> {
+    let invalid = |field, reason: &str| {
+        Err(PostError::InvalidField {
+            field,
+            reason: reason.to_string(),
+        })
+    };
+
+    if title.is_some_and(|title| title.trim().is_empty()) {
+        return invalid("title", "it is empty");
+    }
+
+    if let Some(slug) = slug {
+        if slug.is_empty() {
+            return invalid("slug", "it is empty");
+        }
+        if !slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return invalid("slug", "use only lowercase letters, digits and '-'");
+        }
+        if slug.starts_with('-') || slug.ends_with('-') {
+            return invalid("slug", "it can not start or end with '-'");
+        }
+        // Posts are looked up by slug or ID, a numeric slug would be read as an ID
+        if slug.chars().all(|c| c.is_ascii_digit()) {
+            return invalid("slug", "it can not be only digits");
+        }
+    }
+
+    if let Some(publish_at) = publish_at
+        && publish_at <= Utc::now()
+    {
+        return Err(PostError::PublishAtInPast(publish_at.to_rfc3339()));
+    }
 
     Ok(())
 }
 
+fn read_image(path: &Path) -> Result<PostImage, PostError> {
+    let bytes = fs::read(path)?;
+    // The devlog checked the content decodes, its format names the type better than the extension
+    let mime_type = image::guess_format(&bytes)
+        .map(|format| format.to_mime_type().to_string())
+        .unwrap_or_else(|_| {
+            MimeGuess::from_path(path)
+                .first_or_octet_stream()
+                .essence_str()
+                .to_string()
+        });
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    Ok(PostImage {
+        file_name,
+        mime_type,
+        bytes,
+    })
+}
+
+fn changelog_title(range: &str, push: Option<&PushSummary>) -> String {
+    match push {
+        Some(push) => format!("Version {} ({})", push.version, push.os),
+        None => match range.rsplit_once("..").map_or(range, |(_, to)| to) {
+            "" | "HEAD" => "Changelog".to_string(),
+            to => format!("Changelog {}", to),
+        },
+    }
+}
+
+fn print_post(post: &Post) {
+    println!("  ID: {}", post.id);
+    println!("  Title: {}", post.title);
+    if let Some(slug) = &post.slug {
+        println!("  Slug: {}", slug);
+    }
+    if let Some(url) = &post.url {
+        println!("  URL: {}", url);
+    }
+}
+
+fn status(post: &Post, queue: &[ScheduledPost]) -> &'static str {
+    if !post.draft {
+        "published"
+    } else if post.publish_at.is_some() {
+        "scheduled"
+    } else if queue.iter().any(|entry| entry.post_id == post.id) {
+        "queued"
+    } else {
+        "draft"
+    }
+}
+
 // Function to check if body is text or path to text file
 fn resolve_body(body: String) -> Result<String, PostError> {
     let path = Path::new(&body);
@@ -91,3 +537,40 @@ fn resolve_body(body: String) -> Result<String, PostError> {
 
     Ok(body)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn reason(result: Result<(), PostError>) -> String {
+        result.err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn fields_are_checked_before_sending() {
+        assert!(validate_fields(Some("Update 1.2"), Some("update-1-2"), None).is_ok());
+        assert!(validate_fields(None, None, Some(Utc::now() + Duration::hours(1))).is_ok());
+
+        assert_eq!(
+            reason(validate_fields(Some("  "), None, None)),
+            "Invalid post title: it is empty"
+        );
+        assert_eq!(
+            reason(validate_fields(None, Some("Big Update"), None)),
+            "Invalid post slug: use only lowercase letters, digits and '-'"
+        );
+        assert_eq!(
+            reason(validate_fields(None, Some("-update"), None)),
+            "Invalid post slug: it can not start or end with '-'"
+        );
+        assert_eq!(
+            reason(validate_fields(None, Some("2024"), None)),
+            "Invalid post slug: it can not be only digits"
+        );
+        assert!(matches!(
+            validate_fields(None, None, Some(Utc::now() - Duration::minutes(1))),
+            Err(PostError::PublishAtInPast(_))
+        ));
+    }
+}
@@ -1,23 +1,26 @@
 use std::{
-    path::Path,
-    sync::Arc,
-    time::{Instant, SystemTime, UNIX_EPOCH},
+    fs::{self, File, TryLockError},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use aws_sdk_s3::config::{Credentials, Region};
-use chrono::DateTime;
-use serde::{Deserialize, Serialize};
-use tokio::sync::Semaphore;
-
-use crate::{
-    constants::{COMPLETE_PUSH_URL, REQUEST_UPLOAD_URL, VERIFY_UPLOAD_URL},
-    errors::push::PushError,
-    file_index::{FileEntry, FileIndex, compare_fileindex, generate_fileindex},
-    network::Game,
-    ui::CliUi,
-    utils::{get_api_key, get_target_game, set_target_game},
+use clawdrop::{
+    archive::{ArchiveFile, ArchiveKind, list_files},
+    client::{Game, RaccreativeClient},
+    config::load_config,
+    errors::{Diagnostic, push::PushError},
+    network::{invalidate_games_cache, load_games_list},
+    pusher::{
+        NonUtf8Names, PushEvent, PushOptions, PushParams, PushProgress, PushStage, PushSummary,
+        Pusher,
+    },
+    transfer::{ByteRate, Concurrency, RateLimiter, TransferLimits},
+    utils::{get_api_key, get_config_path, get_target_game, set_target_game},
 };
 
+use crate::ui::{CliUi, SpinnerHandle};
+
 #[derive(Clone)]
 pub struct PushArgs {
     pub id: Option<u64>,
@@ -29,6 +32,13 @@ pub struct PushArgs {
     pub no_bump: bool,
     pub shorthand: Option<String>,
     pub force: bool,
+    pub index: Option<String>,
+    pub index_sample: usize,
+    pub concurrency: Option<Concurrency>,
+    pub limit_rate: Option<ByteRate>,
+    pub wait_stable: Option<u64>,
+    pub skip_unreadable: bool,
+    pub non_utf8: NonUtf8Names,
 }
 
 pub struct ShorthandParams {
@@ -38,539 +48,214 @@ pub struct ShorthandParams {
     pub version: Option<String>,
 }
 
-pub struct PushParams {
-    pub id: u64,
-    pub os: String,
-    pub exe: String,
-    pub version: String,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct TemporaryCredentials {
-    pub access_key_id: String,
-    pub secret_access_key: String,
-    pub expiration: String,
-    pub session_token: String,
-    pub bucket: String,
-    pub prefix: String,
-    pub region: String,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct RequestUploadResponse {
-    pub upload_credentials: TemporaryCredentials,
-    pub delete_credentials: TemporaryCredentials,
-    pub extra_uploads: ExtraUploads,
-    pub extra_downloads: ExtraDownloads,
-    pub original_zip_name: Option<String>,
-    pub upload_id: String,
+// Held for the whole push so two clawdrop processes never push at the same time.
+// The OS releases the lock when the file is closed, even if the process dies
+struct PushLock {
+    _file: File,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct RequestUploadBody {
-    version: String,
-    fileindex: String,
+// Draws pusher events with spinners and progress bars
+struct CliProgress {
+    ui: CliUi,
+    spinner: Mutex<Option<SpinnerHandle>>,
+    from_snapshot: bool,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct VerifyUploadBody {
-    version: String,
-    upload_id: String,
-}
-
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct CompletePushBody {
-    os: String,
-    new_version: String,
-    file_name: String,
-}
+impl CliProgress {
+    fn new(from_snapshot: bool) -> Self {
+        Self {
+            ui: CliUi::new(),
+            spinner: Mutex::new(None),
+            from_snapshot,
+        }
+    }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ExtraUploads {
-    pub manifest: String,
-    pub fileindex: String,
-}
+    fn start_spinner(&self, message: &str) {
+        *self.spinner.lock().unwrap() = Some(self.ui.start_spinner(message));
+    }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ExtraDownloads {
-    pub fileindex: Option<String>,
+    fn stop_spinner(&self) {
+        if let Some(spinner) = self.spinner.lock().unwrap().take() {
+            spinner.stop();
+        }
+    }
 }
 
-#[derive(Serialize)]
-struct Manifest {
-    path: String,
-    version: String,
+impl PushProgress for CliProgress {
+    fn report(&self, event: PushEvent<'_>) {
+        match event {
+            PushEvent::Stage(PushStage::Fileindex) if self.from_snapshot => {
+                self.ui.set_status("Checking fileindex snapshot")
+            }
+            PushEvent::Stage(PushStage::Fileindex) => {
+                self.ui.set_status("Generating local fileindex.json")
+            }
+            PushEvent::WaitingForStable => {
+                self.ui.set_status("Waiting for the build to stop changing")
+            }
+            PushEvent::SkippedUnreadable(files) => {
+                self.stop_spinner();
+                eprintln!(
+                    "\nWarning: {} files or directories could not be read and are left out:",
+                    files.len()
+                );
+                for file in files {
+                    eprintln!("  {}", file);
+                }
+            }
+            PushEvent::NonUtf8Names { names, escaped } => {
+                self.stop_spinner();
+                let action = if escaped {
+                    "are uploaded with escaped names"
+                } else {
+                    "are left out"
+                };
+                eprintln!(
+                    "\nWarning: {} names are not valid UTF-8 and {}:",
+                    names.len(),
+                    action
+                );
+                for name in names {
+                    eprintln!("  {}", name);
+                }
+            }
+            PushEvent::Stage(PushStage::Credentials) => {
+                self.ui.set_status("Requesting credentials")
+            }
+            PushEvent::Stage(PushStage::RemoteFileindex) => {
+                self.ui.set_status("Retrieving remote fileindex.json")
+            }
+            PushEvent::Stage(PushStage::Compare) => self
+                .ui
+                .set_status("Comparing local and remote fileindex for changes"),
+            PushEvent::NoChanges => {
+                self.stop_spinner();
+                println!(
+                    "No changes to upload or delete, you can use --force to ignore this and upload everything"
+                );
+            }
+            PushEvent::Changes(changes) => {
+                self.stop_spinner();
+                println!(
+                    "\n New files: {}, Modified files: {}, Obsolete files: {}",
+                    changes.new_files.len(),
+                    changes.modified_files.len(),
+                    changes.deleted_files.len()
+                );
+            }
+            PushEvent::UploadProgress {
+                done_bytes,
+                total_bytes,
+                speed,
+            } => self
+                .ui
+                .show_progress_bytes(done_bytes, total_bytes, "Uploading files", speed),
+            PushEvent::Stage(PushStage::Verify) => self.start_spinner("Verifying Uploaded files"),
+            PushEvent::Stage(PushStage::Delete) => self.stop_spinner(),
+            PushEvent::DeleteProgress { done, total } => {
+                self.ui
+                    .show_progress_count(done, total, "Deleting remote obsolete files")
+            }
+            PushEvent::UploadFinished | PushEvent::DeleteFinished => self.ui.finish_progress(),
+            PushEvent::Stage(PushStage::Manifest) => {
+                self.start_spinner("Uploading new manifest.json and fileindex.json")
+            }
+            PushEvent::Stage(PushStage::Complete) => self.ui.set_status("Finalizing"),
+            PushEvent::CredentialsRefreshed => self
+                .ui
+                .print_line("Upload credentials were about to expire, renewed them"),
+            PushEvent::Finished => self.stop_spinner(),
+            PushEvent::Stage(PushStage::Upload) => {}
+        }
+    }
 }
 
-pub async fn run(args: PushArgs) -> Result<(), PushError> {
-    let ui = CliUi::new();
+pub async fn run(args: PushArgs) -> Result<PushSummary, PushError> {
+    let progress = CliProgress::new(args.index.is_some());
+    let client = RaccreativeClient::new()?.with_api_key(get_api_key()?);
 
     // First of all, we check if target game exist and update its data doing a new set
-    let mut spinner = ui.start_spinner("Checking target game");
+    progress.start_spinner("Checking target game");
 
     let target_game = match get_target_game()? {
         Some(target) => {
-            ui.set_status("Updating target data");
-            let game = tokio::task::spawn_blocking(move || set_target_game(target.id.to_string()))
-                .await??;
+            progress.ui.set_status("Updating target data");
+            // Versions change outside of this machine, the cache is only a fallback when offline
+            let games = load_games_list(&client, true).await?;
+            let warning = games.offline_warning();
+            let game = set_target_game(&client, games, target.id.to_string()).await?;
+
+            if let Some(warning) = warning {
+                progress.stop_spinner();
+                eprintln!("Warning: {}", warning);
+            }
             Some(game)
         }
         None => None,
     };
 
-    // 1. We parse and resolve the params
+    // Then we parse and resolve the params, the pusher does the rest
     let params: PushParams = resolve_push_params(args.clone(), target_game)?;
-
-    let api_key: String = get_api_key()?;
-    let http_client = build_client()?;
-
-    // 2. We generate fileindex.json local with --ignore if any
-    ui.set_status("Generating local fileindex.json");
-
-    let fileindex_local = generate_fileindex(&args.path, &args.ignore)?;
-    let fileindex_json_string = serde_json::to_string(&fileindex_local)?;
-
-    // 3. We request the temporal credentials and use them in S3
-    ui.set_status("Requesting credentials");
-
-    let response = request_upload(
-        &http_client,
-        &api_key,
-        params.id.clone(),
-        params.os.clone(),
-        fileindex_json_string,
-    )
-    .await?;
-
-    let RequestUploadResponse {
-        delete_credentials,
-        upload_credentials,
-        extra_uploads,
-        extra_downloads,
-        original_zip_name,
-        upload_id,
-    } = response;
-
-    let expiration_time: Option<SystemTime> =
-        match DateTime::parse_from_rfc3339(&upload_credentials.expiration) {
-            Ok(dt) => {
-                let timestamp = dt.timestamp();
-                Some(UNIX_EPOCH + std::time::Duration::from_secs(timestamp as u64))
-            }
-            Err(_) => None,
-        };
-
-    // We have separate clients for upload and deleting files for better security
-    let upload_config = aws_sdk_s3::Config::builder()
-        .region(Region::new(upload_credentials.region.clone()))
-        .credentials_provider(Credentials::new(
-            upload_credentials.access_key_id,
-            upload_credentials.secret_access_key,
-            Some(upload_credentials.session_token),
-            expiration_time,
-            "ApiTemporaryCredentials",
-        ))
-        .behavior_version_latest()
-        .build();
-
-    let delete_config = aws_sdk_s3::Config::builder()
-        .region(Region::new(delete_credentials.region.clone()))
-        .credentials_provider(Credentials::new(
-            delete_credentials.access_key_id,
-            delete_credentials.secret_access_key,
-            Some(delete_credentials.session_token),
-            expiration_time,
-            "ApiTemporaryCredentials",
-        ))
-        .behavior_version_latest()
-        .build();
-
-    let s3_client_upload = aws_sdk_s3::Client::from_conf(upload_config);
-    let s3_client_delete = aws_sdk_s3::Client::from_conf(delete_config);
-
-    // 4. We download remote fileindex.json for local comparison (if no fileindex in remote we create an empty one)
-    ui.set_status("Retrieving remote fileindex.json");
-
-    let fileindex_remote: FileIndex = if let Some(url) = &extra_downloads.fileindex {
-        let res_fileindex = http_client.get(url).send().await?;
-        match res_fileindex.status() {
-            reqwest::StatusCode::NOT_FOUND => FileIndex::default(),
-            status if status.is_success() => res_fileindex.json().await?,
-            status => {
-                let text = res_fileindex
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "<no body>".into());
-                return Err(PushError::ServerError {
-                    code: status.as_u16(),
-                    message: text,
-                });
-            }
-        }
-    } else {
-        FileIndex::default()
-    };
-
-    // 5. Compare local and remote fileindex to see what changed (if --force upload all files)
-    ui.set_status("Comparing local and remote fileindex for changes");
-
-    let changes = compare_fileindex(&fileindex_local, &fileindex_remote, &original_zip_name);
-    let files_to_upload = changes.to_upload(args.force, &fileindex_local);
-
-    spinner.stop();
-    if files_to_upload.is_empty() && changes.deleted_files.is_empty() {
-        println!(
-            "No changes to upload or delete, you can use --force to ignore this and upload everything"
-        );
-        return Ok(());
-    }
-
-    println!(
-        "\n New files: {}, Modified files: {}, Obsolete files: {}",
-        changes.new_files.len(),
-        changes.modified_files.len(),
-        changes.deleted_files.len()
-    );
-
-    // 6. Upload new/modified files with progress bar (everything if --force except --ignore [step 4 & 5])
-    let total = files_to_upload.len();
-    let base_path = std::path::PathBuf::from(&args.path);
-    upload_files_if_any(
-        &ui,
-        &s3_client_upload,
-        total,
-        &files_to_upload,
-        upload_credentials.prefix,
-        &upload_credentials.bucket,
-        &base_path,
-    )
-    .await?;
-
-    spinner = ui.start_spinner("Verifying Uploaded files");
-    verify_upload(
-        &http_client,
-        &api_key,
-        params.os.clone(),
-        upload_id,
-        params.id,
-    )
-    .await?;
-
-    spinner.stop();
-
-    // 7. Delete obsolete remote files (except manifest.json, *.zip)
-    let total_to_delete = changes.deleted_files.len();
-    delete_files_if_any(
-        &ui,
-        &s3_client_delete,
-        total_to_delete,
-        &changes.deleted_files,
-        delete_credentials.prefix,
-        &delete_credentials.bucket,
-    )
-    .await?;
-
-    // 8. Upload manifest.json and fileindex.json with presigned urls
-    spinner = ui.start_spinner("Uploading new manifest.json and fileindex.json");
-
-    let manifest = Manifest {
-        path: params.exe.clone(),
-        version: params.version.clone(),
+    let _lock = acquire_push_lock(params.id, &params.os)?;
+
+    // Transfer settings: flags first, then config.json, then defaults
+    let config = load_config()?;
+    let limits = TransferLimits {
+        concurrency: args.concurrency.or(config.concurrency).unwrap_or_default(),
+        limiter: args
+            .limit_rate
+            .or(config.limit_rate)
+            .map(|rate| Arc::new(RateLimiter::new(rate))),
     };
-    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
-    let fileindex_json = serde_json::to_vec_pretty(&fileindex_local)?;
-
-    upload_extra_files(
-        &http_client,
-        extra_uploads.manifest,
-        manifest_json,
-        extra_uploads.fileindex,
-        fileindex_json,
-    )
-    .await?;
-
-    // 9. We update the new version in database and close the upload
-    ui.set_status("Finalizing");
-
-    let complete_push_body = CompletePushBody {
-        os: params.os.clone(),
-        new_version: params.version.clone(),
-        file_name: original_zip_name
-            .clone()
-            .unwrap_or_else(|| format!("{}-{}.zip", params.id, params.os)),
-    };
-
-    complete_push(&http_client, api_key, params.id, complete_push_body).await?;
-
-    spinner.stop();
 
-    Ok(())
-}
-
-// Build client for async (not the same as crate::network::build_client)
-fn build_client() -> Result<reqwest::Client, reqwest::Error> {
-    reqwest::Client::builder().use_rustls_tls().build()
-}
-
-async fn request_upload(
-    client: &reqwest::Client,
-    api_key: &String,
-    id: u64,
-    os: String,
-    fileindex: String,
-) -> Result<RequestUploadResponse, PushError> {
-    let body = RequestUploadBody {
-        version: os,
-        fileindex: fileindex,
+    let options = PushOptions {
+        path: PathBuf::from(&args.path),
+        ignore: args.ignore,
+        force: args.force,
+        index: args.index.map(PathBuf::from),
+        index_sample: args.index_sample,
+        wait_stable: args.wait_stable.map(Duration::from_secs),
+        skip_unreadable: args.skip_unreadable,
+        non_utf8: args.non_utf8,
     };
 
-    let res = client
-        .post(REQUEST_UPLOAD_URL.replace("{id}", &id.to_string()))
-        .header("x-api-key", api_key)
-        .json(&body)
-        .send()
+    let summary = Pusher::new(client)
+        .with_limits(limits)
+        .with_progress(progress)
+        .push(&params, &options)
         .await?;
 
-    let res_to_parse = match res.status() {
-        reqwest::StatusCode::FORBIDDEN => return Err(PushError::UnauthorizedToUpload),
-        reqwest::StatusCode::NOT_FOUND => return Err(PushError::GameNotFound),
-        reqwest::StatusCode::BAD_REQUEST => return Err(PushError::FileSizeLimitReach),
-        status if !status.is_success() => {
-            let text = res.text().await.unwrap_or_else(|_| "<no body>".into());
-            return Err(PushError::ServerError {
-                code: status.as_u16(),
-                message: text,
-            });
-        }
-        _ => res,
-    };
+    // The live version changed, the next command must not read the old one from the cache
+    invalidate_games_cache();
 
-    let response: RequestUploadResponse = res_to_parse.json().await?;
-    Ok(response)
-}
-
-async fn upload_extra_files(
-    client: &reqwest::Client,
-    manifest_url: String,
-    manifest_json: Vec<u8>,
-    fileindex_url: String,
-    fileindex_json: Vec<u8>,
-) -> Result<(), PushError> {
-    client
-        .put(manifest_url)
-        .header("content-type", "application/json")
-        .body(manifest_json)
-        .send()
-        .await
-        .map_err(|e| PushError::S3Error {
-            message: format!("Failed uploading manifest.json: {:?}", e),
-        })?;
-
-    client
-        .put(fileindex_url)
-        .header("content-type", "application/json")
-        .body(fileindex_json)
-        .send()
-        .await
-        .map_err(|e| PushError::S3Error {
-            message: format!("Failed uploading fileindex.json: {:?}", e),
-        })?;
-
-    Ok(())
-}
-
-async fn upload_files_if_any(
-    ui: &CliUi,
-    s3_client: &aws_sdk_s3::Client,
-    total: usize,
-    files_to_upload: &Vec<FileEntry>,
-    prefix: String,
-    bucket: &String,
-    base_path: &Path,
-) -> Result<(), PushError> {
-    if total == 0 {
-        return Ok(());
-    }
-
-    let mut prepared_files = Vec::with_capacity(files_to_upload.len());
-    let mut total_bytes: u64 = 0;
-    for entry in files_to_upload {
-        let local_path = base_path.join(&entry.path);
-        let size = std::fs::metadata(&local_path)?.len();
-        total_bytes += size;
-        prepared_files.push((entry.path.clone(), local_path, size));
-    }
-
-    ui.show_progress_bytes(0, total_bytes, "Uploading files", None);
-
-    let (tx, mut rx) = tokio::sync::mpsc::channel(prepared_files.len());
-    let concurrency = 8;
-    let sem = Arc::new(Semaphore::new(concurrency));
-
-    // Spawn concurrent tasks
-    for (file_path, local_path, size) in prepared_files {
-        let s3_client = s3_client.clone();
-        let bucket = bucket.clone();
-        let key = format!("{}{}", prefix, file_path);
-        let tx = tx.clone();
-        let sem = sem.clone();
-
-        tokio::spawn(async move {
-            let _permit = sem.acquire_owned().await.unwrap();
-
-            let body = aws_sdk_s3::primitives::ByteStream::from_path(&local_path)
-                .await
-                .map_err(|e| PushError::S3Error {
-                    message: format!("Failed to read {}: {:?}", file_path, e),
-                })?;
-
-            s3_client
-                .put_object()
-                .bucket(&bucket)
-                .key(&key)
-                .checksum_algorithm(aws_sdk_s3::types::ChecksumAlgorithm::Sha256)
-                .body(body)
-                .send()
-                .await
-                .map_err(|e| PushError::S3Error {
-                    message: format!("Failed uploading {}: {:?}", file_path, e),
-                })?;
-
-            // Send progress to main thread
-            tx.send(size).await.unwrap();
-
-            Ok::<(), PushError>(())
-        });
-    }
-
-    drop(tx);
-
-    // Update UI from main thread while results arrive
-    let mut done_bytes: u64 = 0;
-    let start = Instant::now();
-
-    while let Some(uploaded) = rx.recv().await {
-        done_bytes += uploaded;
-        let elapsed = start.elapsed().as_secs_f64();
-        // Speed could be inaccurate... But will be solved when delta-patching is implemented
-        let speed = (elapsed > 0.0).then(|| (done_bytes as f64 / elapsed) as u64);
-
-        ui.show_progress_bytes(done_bytes, total_bytes, "Uploading files", speed);
+    // The build is live, files left over from the previous one are only reported
+    if !summary.delete_failures.is_empty() {
+        let error = PushError::DeleteFailed(summary.delete_failures.clone());
+        eprintln!("\nWarning: {}", error);
+        if let Some(hint) = error.hint() {
+            eprintln!("  → {}", hint);
+        }
     }
 
-    ui.finish_progress();
-    Ok(())
+    Ok(summary)
 }
 
-async fn delete_files_if_any(
-    ui: &CliUi,
-    s3_client: &aws_sdk_s3::Client,
-    total: usize,
-    files_to_delete: &Vec<FileEntry>,
-    prefix: String,
-    bucket: &String,
-) -> Result<(), PushError> {
-    if total <= 0 {
-        return Ok(());
-    }
-
-    ui.show_progress_count(0, total, "Deleting remote obsolete files");
-    let mut deleted_done = 0;
-
-    for entry in files_to_delete {
-        let key = format!("{}{}", prefix, entry.path);
-
-        s3_client
-            .delete_object()
-            .bucket(bucket)
-            .key(&key)
-            .send()
-            .await
-            .map_err(|e| PushError::S3Error {
-                message: format!("Failed deleting {}: {:?}", entry.path, e),
-            })?;
-
-        deleted_done += 1;
-        ui.show_progress_count(deleted_done, total, "Deleting remote obsolete files");
+// One push at a time per game and OS, pushes of other builds can run alongside
+fn acquire_push_lock(id: u64, os: &str) -> Result<PushLock, PushError> {
+    let path = get_config_path().join(format!("push-{}-{}.lock", id, os));
+    fs::create_dir_all(get_config_path())?;
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(PushLock { _file: file }),
+        Err(TryLockError::WouldBlock) => Err(PushError::PushInProgress),
+        Err(TryLockError::Error(e)) => Err(e.into()),
     }
-
-    ui.finish_progress();
-    Ok(())
-}
-
-async fn verify_upload(
-    client: &reqwest::Client,
-    api_key: &String,
-    os: String,
-    upload_id: String,
-    id: u64,
-) -> Result<(), PushError> {
-    let verify_body = VerifyUploadBody {
-        version: os,
-        upload_id: upload_id,
-    };
-
-    let verify_res = client
-        .put(VERIFY_UPLOAD_URL.replace("{id}", &id.to_string()))
-        .header("x-api-key", api_key)
-        .json(&verify_body)
-        .send()
-        .await?;
-
-    match verify_res.status() {
-        reqwest::StatusCode::BAD_REQUEST => return Err(PushError::FileindexMismatch),
-        status if !status.is_success() => {
-            let text = verify_res
-                .text()
-                .await
-                .unwrap_or_else(|_| "<no body>".into());
-            return Err(PushError::ServerError {
-                code: status.as_u16(),
-                message: text,
-            });
-        }
-        _ => (),
-    };
-
-    Ok(())
-}
-
-async fn complete_push(
-    client: &reqwest::Client,
-    api_key: String,
-    id: u64,
-    complete_push_body: CompletePushBody,
-) -> Result<(), PushError> {
-    let complete_push_res = client
-        .put(COMPLETE_PUSH_URL.replace("{id}", &id.to_string()))
-        .header("x-api-key", api_key)
-        .json(&complete_push_body)
-        .send()
-        .await?;
-
-    match complete_push_res.status() {
-        reqwest::StatusCode::FORBIDDEN => return Err(PushError::UnauthorizedToUpload),
-        status if !status.is_success() => {
-            let text = complete_push_res
-                .text()
-                .await
-                .unwrap_or_else(|_| "<no body>".into());
-            return Err(PushError::ServerError {
-                code: status.as_u16(),
-                message: text,
-            });
-        }
-        _ => (),
-    };
-
-    Ok(())
 }
 
 // Checks if given executable file exists and returns a fixed path
@@ -603,6 +288,25 @@ fn find_executable(base_path: &Path, exe_name: &str) -> Option<String> {
     None
 }
 
+// Same lookup as find_executable, in the file names of an archive build
+fn find_archive_executable(files: &[ArchiveFile], exe_name: &str) -> Option<String> {
+    let names: Vec<String> = files
+        .iter()
+        .map(|file| String::from_utf8_lossy(&file.name).into_owned())
+        .collect();
+
+    // Root first
+    if names.iter().any(|name| name == exe_name) {
+        return Some(exe_name.to_string());
+    }
+
+    // Subdirectories
+    names.into_iter().find(|name| {
+        name.split_once('/')
+            .is_some_and(|(dir, rest)| !dir.is_empty() && rest == exe_name)
+    })
+}
+
 pub fn resolve_push_params(
     args: PushArgs,
     target_game: Option<Game>,
@@ -635,8 +339,12 @@ pub fn resolve_push_params(
         .or(args.exe.as_deref())
         .ok_or(PushError::MissingExecutableName)?;
 
-    let exe_path =
-        find_executable(Path::new(&args.path), exe).ok_or(PushError::MissingExecutableFile)?;
+    let build_path = Path::new(&args.path);
+    let exe_path = match ArchiveKind::detect(build_path)? {
+        Some(kind) => find_archive_executable(&list_files(build_path, kind)?, exe),
+        None => find_executable(build_path, exe),
+    }
+    .ok_or(PushError::MissingExecutableFile)?;
 
     let version = short_hand_params
         .and_then(|sh| sh.version.clone())
@@ -657,20 +365,14 @@ pub fn resolve_push_params(
 }
 
 fn target_version_for_os(target_game: &Game, os: &str, no_bump: bool) -> Option<String> {
-    let base_version = match os {
-        "windows" => target_game.windows_version.as_ref(),
-        "linux" => target_game.linux_version.as_ref(),
-        "mac" => target_game.mac_version.as_ref(),
-        "html" => target_game.html_version.as_ref(),
-        _ => return None,
-    };
-
-    base_version.map(|v| if no_bump { v.clone() } else { bump_version(v) })
+    target_game
+        .version_for(os)
+        .map(|v| if no_bump { v.clone() } else { bump_version(v) })
 }
 
 // This function does not cover al cases
-fn bump_version(version: &str) -> String {
-    if let Some(pos) = version.rfind(|c: char| c.is_ascii_digit() == false) {
+pub fn bump_version(version: &str) -> String {
+    if let Some(pos) = version.rfind(|c: char| !c.is_ascii_digit()) {
         let (left, right) = version.split_at(pos + 1);
         if let Ok(num) = right.parse::<u64>() {
             let width = right.len();
@@ -1,7 +1,19 @@
-use crate::{errors::set::SetError, green, utils::set_target_game};
+use clawdrop::{
+    client::RaccreativeClient,
+    errors::set::SetError,
+    green,
+    network::load_games_list,
+    utils::{get_api_key, set_target_game},
+};
 
-pub fn run(id: String) -> Result<(), SetError> {
-    let target_game = set_target_game(id)?;
+pub async fn run(id: String, refresh: bool) -> Result<(), SetError> {
+    let client = RaccreativeClient::new()?.with_api_key(get_api_key()?);
+    let games = load_games_list(&client, refresh).await?;
+    if let Some(warning) = games.offline_warning() {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let target_game = set_target_game(&client, games, id).await?;
 
     println!(
         "Game '{}' set as default target {}",
@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use clawdrop::{
+    archive::ArchiveKind,
+    client::{GameDetails, RaccreativeClient},
+    config::load_project_config,
+    errors::status::StatusError,
+    file_index::{compare_fileindex, generate_fileindex},
+    green,
+    network::load_games_list,
+    red,
+    utils::{get_api_key, get_target_game, set_target_game},
+};
+
+use crate::commands::list::{PLATFORMS, format_time};
+
+pub async fn run() -> Result<(), StatusError> {
+    let api_key = get_api_key()?;
+    let target = get_target_game()?.ok_or(StatusError::NoTarget)?;
+    let client = RaccreativeClient::new()?.with_api_key(api_key.clone());
+
+    match client.verify_api_key(&api_key).await {
+        Ok(true) => println!("API key:    {}", green!("✓ valid")),
+        Ok(false) => println!(
+            "API key:    {}",
+            red!("X invalid or expired, run clawdrop auth")
+        ),
+        Err(e) => println!("API key:    could not be checked ({})", e),
+    }
+
+    // Always asks the API, the cache is only used when it cannot be reached
+    let games = load_games_list(&client, true).await?;
+    if let Some(warning) = games.offline_warning() {
+        eprintln!("Warning: {}", warning);
+    }
+    let game = set_target_game(&client, games, target.id.to_string()).await?;
+
+    println!("Target:     {} (ID {})", game.title, game.id);
+
+    let details = match client.game_details(game.id).await {
+        Ok(details) => Some(details),
+        Err(e) => {
+            eprintln!("Warning: could not get the build details: {}", e);
+            None
+        }
+    };
+    if let Some(url) = details.as_ref().and_then(|d| d.url.as_deref()) {
+        println!("URL:        {}", url);
+    }
+    println!();
+
+    println!(
+        "{:<10} | {:<15} | {:<16}",
+        "Platform", "Live version", "Last push"
+    );
+    println!("{:-<10}-+-{:-<15}-+-{:-<16}", "", "", "");
+    for os in PLATFORMS {
+        let pushed_at = details
+            .as_ref()
+            .and_then(|d| d.builds.iter().find(|build| build.os == os))
+            .and_then(|build| build.pushed_at.as_deref())
+            .map(format_time)
+            .unwrap_or_else(|| "-".to_string());
+
+        println!(
+            "{:<10} | {:<15} | {:<16}",
+            os,
+            game.version_for(os).map_or("-", |v| v.as_str()),
+            pushed_at
+        );
+    }
+    println!();
+
+    print_local_build(&client, details.as_ref()).await
+}
+
+// Compares the build directory from clawdrop.json with the live fileindex of its platform
+async fn print_local_build(
+    client: &RaccreativeClient,
+    details: Option<&GameDetails>,
+) -> Result<(), StatusError> {
+    let project = load_project_config()?;
+
+    let (Some(path), Some(os)) = (project.path, project.os) else {
+        println!("Local build: not configured (set path and os in clawdrop.json)");
+        return Ok(());
+    };
+
+    println!("Local build: {} ({})", path, os);
+
+    // A .zip or .tar.gz of the build is indexed in place, like push does
+    match ArchiveKind::detect(Path::new(&path)) {
+        Ok(Some(_)) => {}
+        Ok(None) if Path::new(&path).is_dir() => {}
+        Ok(None) => {
+            println!("  {}", red!("X directory or archive not found"));
+            return Ok(());
+        }
+        Err(e) => {
+            println!("  {}", red!(format!("X {}", e)));
+            return Ok(());
+        }
+    }
+
+    let Some(details) = details else {
+        println!("  could not be compared without the build details");
+        return Ok(());
+    };
+
+    let build = details.builds.iter().find(|build| build.os == os);
+    let Some(url) = build.and_then(|build| build.fileindex.as_deref()) else {
+        println!(
+            "  nothing live for {} yet, clawdrop push uploads every file",
+            os
+        );
+        return Ok(());
+    };
+
+    // Like the checks above, a failure here only ends this section
+    let local = match generate_fileindex(&path, &project.ignore) {
+        Ok(local) => local,
+        Err(e) => {
+            println!("  {}", red!(format!("X could not index the build: {}", e)));
+            return Ok(());
+        }
+    };
+    let remote = match client.download_fileindex(url).await {
+        Ok(remote) => remote,
+        Err(e) => {
+            println!(
+                "  {}",
+                red!(format!("X could not download the live fileindex: {}", e))
+            );
+            return Ok(());
+        }
+    };
+    let changes = compare_fileindex(
+        &local,
+        &remote,
+        &build.and_then(|build| build.original_zip_name.clone()),
+    );
+
+    let differ =
+        changes.new_files.len() + changes.modified_files.len() + changes.deleted_files.len();
+    if differ == 0 {
+        println!("  {}", green!("✓ up to date with the live build"));
+    } else {
+        println!(
+            "  {} file{} differ ({} new, {} changed, {} removed), clawdrop push uploads them",
+            differ,
+            if differ == 1 { "" } else { "s" },
+            changes.new_files.len(),
+            changes.modified_files.len(),
+            changes.deleted_files.len()
+        );
+    }
+
+    Ok(())
+}
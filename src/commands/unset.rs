@@ -1,6 +1,6 @@
 use std::{fs, io};
 
-use crate::{green, utils::get_config_path};
+use clawdrop::{green, utils::get_config_path};
 
 pub fn run() -> Result<(), io::Error> {
     let path = get_config_path().join("target.json");
@@ -13,4 +13,3 @@ pub fn run() -> Result<(), io::Error> {
 
     Ok(())
 }
- 
\ No newline at end of file
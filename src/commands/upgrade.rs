@@ -1,9 +1,20 @@
 use self_update::cargo_crate_version;
 
-use crate::green;
+use clawdrop::{errors::upgrade::UpgradeError, green};
 use std::io::{self, Write};
 
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+type UpgradeResult = Result<(), UpgradeError>;
+
+// self_update makes blocking requests, they get their own thread instead of stalling the runtime
+pub async fn run() -> UpgradeResult {
+    tokio::task::spawn_blocking(upgrade).await?
+}
+
+pub async fn check_for_updates() -> UpgradeResult {
+    tokio::task::spawn_blocking(print_available_update).await?
+}
+
+fn upgrade() -> UpgradeResult {
     let updater = self_update::backends::github::Update::configure()
         .repo_owner("raccreative")
         .repo_name("clawdrop")
@@ -59,3 +70,26 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+fn print_available_update() -> UpgradeResult {
+    let current_version = cargo_crate_version!();
+    let release = self_update::backends::github::Update::configure()
+        .repo_owner("raccreative")
+        .repo_name("clawdrop")
+        .bin_name("clawdrop")
+        .current_version(current_version)
+        .build()?
+        .get_latest_release()?;
+
+    let latest_version = release.version.trim_start_matches('v');
+    let update_available = latest_version != current_version;
+
+    if update_available {
+        println!(
+            "A new version {} is available! (current: {}) Run 'clawdrop upgrade' to update",
+            latest_version, current_version
+        );
+    }
+
+    Ok(())
+}
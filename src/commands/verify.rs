@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use clawdrop::{
+    client::{RaccreativeClient, live_object_url},
+    config::load_project_config,
+    errors::verify::VerifyError,
+    file_index::SnapshotMismatch,
+    green,
+    utils::{get_api_key, get_target_game},
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::ui::CliUi;
+
+// HEAD requests in flight at once, they carry no body
+const CONCURRENCY: usize = 16;
+
+pub async fn remote(id: Option<u64>, os: Option<String>) -> Result<(), VerifyError> {
+    let project = load_project_config()?;
+    let id = match id.or(project.id) {
+        Some(id) => id,
+        None => get_target_game()?.ok_or(VerifyError::NoTarget)?.id,
+    };
+    let os = os.or(project.os);
+
+    let client = RaccreativeClient::new()?.with_api_key(get_api_key()?);
+    let details = client.game_details(id).await?;
+
+    let builds: Vec<_> = details
+        .builds
+        .into_iter()
+        .filter(|build| os.as_ref().is_none_or(|os| &build.os == os))
+        .filter_map(|build| build.fileindex.clone().map(|url| (build, url)))
+        .collect();
+    if builds.is_empty() {
+        return Err(VerifyError::NothingLive(match os {
+            Some(os) => format!("for {}", os),
+            None => format!("for {}", details.title),
+        }));
+    }
+
+    let ui = CliUi::new();
+    let mut mismatches = Vec::new();
+
+    for (build, url) in builds {
+        let fileindex = client.download_fileindex(&url).await?;
+        if fileindex.files.is_empty() {
+            println!(
+                "{} {} has an empty fileindex, nothing to check",
+                build.os, build.version
+            );
+            continue;
+        }
+        let base_url =
+            reqwest::Url::parse(&url).map_err(|_| VerifyError::InvalidFileindexUrl(url.clone()))?;
+
+        // Only what the fileindex lists is checked. Listing the prefix for leftovers needs S3
+        // credentials, and the API only hands them out with a new upload session
+        let sem = Arc::new(Semaphore::new(CONCURRENCY));
+        let mut tasks = JoinSet::new();
+        for entry in fileindex.files {
+            let client = client.clone();
+            let url = live_object_url(&base_url, &entry.path);
+            let sem = sem.clone();
+
+            tasks.spawn(async move {
+                let _permit = sem.acquire_owned().await.unwrap();
+                let object = client.head_live_object(url).await;
+                (entry, object)
+            });
+        }
+
+        let total = tasks.len();
+        let title = format!("Checking {} {} ({} files)", build.os, build.version, total);
+        let mut done = 0;
+        let mut without_checksum = Vec::new();
+        let mut build_mismatches = Vec::new();
+
+        ui.show_progress_count(0, total, &title);
+        while let Some(joined) = tasks.join_next().await {
+            let (entry, object) = joined.map_err(std::io::Error::other)?;
+
+            let reason = match object? {
+                None => Some("missing in live build"),
+                Some(object) if object.size != Some(entry.size) => Some("size differs"),
+                Some(object) => match (object.checksum_sha256, entry.checksum_sha256()) {
+                    (Some(remote), Some(local)) if remote != local => Some("checksum differs"),
+                    (Some(_), _) => None,
+                    (None, _) => {
+                        without_checksum.push(entry.path.clone());
+                        None
+                    }
+                },
+            };
+            if let Some(reason) = reason {
+                build_mismatches.push(SnapshotMismatch {
+                    path: format!("{}: {}", build.os, entry.path),
+                    reason,
+                });
+            }
+
+            done += 1;
+            ui.show_progress_count(done, total, &title);
+        }
+        ui.finish_progress();
+
+        // Objects uploaded before checksums were sent, pushing with --force stores one
+        if !without_checksum.is_empty() {
+            without_checksum.sort();
+            println!(
+                "Warning: {} objects have no stored SHA-256, only their size was checked:",
+                without_checksum.len()
+            );
+            for path in &without_checksum {
+                println!("  {}: {}", build.os, path);
+            }
+        }
+        mismatches.append(&mut build_mismatches);
+    }
+
+    if !mismatches.is_empty() {
+        mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+        for mismatch in &mismatches {
+            println!("  {}", mismatch);
+        }
+        return Err(VerifyError::Mismatch(mismatches));
+    }
+
+    println!("Every live object matches its fileindex {}", green!("✓ OK"));
+    Ok(())
+}
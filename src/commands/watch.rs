@@ -0,0 +1,207 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::Local;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tokio::{sync::mpsc, time::Instant};
+
+use clawdrop::{
+    errors::push::PushError,
+    file_index::{build_ignore_set, is_ignored},
+    green, red,
+    utils::format_bytes,
+};
+use globset::GlobSet;
+
+use crate::commands::push::{self, PushArgs, bump_version};
+
+pub async fn run(mut args: PushArgs, quiet_period: u64) -> Result<(), PushError> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let _ = tx.send(res);
+    })?;
+    watcher.watch(Path::new(&args.path), RecursiveMode::Recursive)?;
+
+    let filter = EventFilter {
+        roots: watch_roots(Path::new(&args.path)),
+        ignore_set: build_ignore_set(&args.ignore)?,
+    };
+    let quiet = Duration::from_secs(quiet_period);
+    println!(
+        "Watching {} for changes, pushing after {}s without changes (Ctrl+C to stop)",
+        args.path, quiet_period
+    );
+
+    loop {
+        // We wait for the first change and then until the export has been quiet for the whole period
+        loop {
+            match rx.recv().await {
+                Some(event) => {
+                    if filter.is_change(event) {
+                        break;
+                    }
+                }
+                None => return Ok(()),
+            }
+        }
+
+        let mut deadline = Instant::now() + quiet;
+        loop {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(event)) => {
+                    if filter.is_change(event) {
+                        deadline = Instant::now() + quiet;
+                    }
+                }
+                Ok(None) => return Ok(()),
+                Err(_) => break,
+            }
+        }
+
+        // A failed cycle does not stop watching, the next export will try again
+        match push::run(args.clone()).await {
+            Ok(summary) => {
+                let pushed = summary.uploaded_files > 0 || summary.deleted_files > 0;
+                if pushed {
+                    println!(
+                        "[{}] {} {} ({}) live: {} uploaded ({}), {} deleted",
+                        Local::now().format("%H:%M:%S"),
+                        green!("✓"),
+                        summary.version,
+                        summary.os,
+                        summary.uploaded_files,
+                        format_bytes(summary.uploaded_bytes),
+                        summary.deleted_files
+                    );
+
+                    // Next cycle goes one version ahead even without a target to bump from
+                    args.version = Some(bump_version(&summary.version));
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "\n[{}] {} {}",
+                    Local::now().format("%H:%M:%S"),
+                    red!("X Push failed:"),
+                    e
+                );
+            }
+        }
+
+        println!("Watching {} for changes...", args.path);
+    }
+}
+
+struct EventFilter {
+    roots: Vec<PathBuf>,
+    ignore_set: GlobSet,
+}
+
+impl EventFilter {
+    // Reads done by hashing trigger access events and ignored files never reach the build,
+    // only real changes to pushed files should start a push
+    fn is_change(&self, event: notify::Result<Event>) -> bool {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                // A single failed event (e.g. a queue overflow) is not worth ending the session over
+                eprintln!(
+                    "[{}] Warning: file watcher error, still watching: {}",
+                    Local::now().format("%H:%M:%S"),
+                    e
+                );
+                return false;
+            }
+        };
+
+        if matches!(event.kind, EventKind::Access(_)) {
+            return false;
+        }
+
+        event.paths.is_empty()
+            || event
+                .paths
+                .iter()
+                .any(|path| match self.relative_path(path) {
+                    Some(relative) => {
+                        relative.is_empty() || !is_ignored(&self.ignore_set, &relative)
+                    }
+                    None => true,
+                })
+    }
+
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = self
+            .roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())?;
+        Some(
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+// Events may carry either the path as given or its resolved form depending on the platform
+fn watch_roots(path: &Path) -> Vec<PathBuf> {
+    let mut roots = vec![path.to_path_buf()];
+    if let Ok(canonical) = path.canonicalize()
+        && canonical != path
+    {
+        roots.push(canonical);
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+
+    fn filter(ignore: &[&str]) -> EventFilter {
+        EventFilter {
+            roots: vec![PathBuf::from("/export")],
+            ignore_set: build_ignore_set(&ignore.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+                .unwrap(),
+        }
+    }
+
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Result<Event> {
+        let mut event = Event::new(kind);
+        for path in paths {
+            event = event.add_path(PathBuf::from(path));
+        }
+        Ok(event)
+    }
+
+    #[test]
+    fn errors_and_access_events_are_not_changes() {
+        let filter = filter(&[]);
+        assert!(!filter.is_change(Err(notify::Error::generic("queue overflow"))));
+        assert!(!filter.is_change(event(
+            EventKind::Access(AccessKind::Any),
+            &["/export/game.pck"]
+        )));
+        assert!(filter.is_change(event(
+            EventKind::Modify(ModifyKind::Any),
+            &["/export/game.pck"]
+        )));
+    }
+
+    #[test]
+    fn ignored_paths_are_not_changes() {
+        let filter = filter(&["*.tmp", "logs"]);
+        let create = EventKind::Create(CreateKind::File);
+        assert!(!filter.is_change(event(create, &["/export/game.pck.tmp"])));
+        assert!(!filter.is_change(event(create, &["/export/logs/today/run.log"])));
+        assert!(filter.is_change(event(create, &["/export/logs/run.log", "/export/game.pck"])));
+        // The watched path itself, e.g. a watched archive being rewritten
+        assert!(filter.is_change(event(create, &["/export"])));
+    }
+}
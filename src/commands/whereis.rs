@@ -1,7 +1,6 @@
-use std::env;
-use std::error::Error;
+use std::{env, io};
 
-pub fn run() -> Result<(), Box<dyn Error>> {
+pub fn run() -> Result<(), io::Error> {
     let exe_path = env::current_exe()?;
     println!("{}", exe_path.display());
     Ok(())
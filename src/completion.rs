@@ -0,0 +1,34 @@
+use clap_complete::CompletionCandidate;
+
+use clawdrop::network::read_cached_games_list;
+
+// Ids and url slugs of the cached developed games, for `set <id|slug>`
+pub fn game_candidates() -> Vec<CompletionCandidate> {
+    let Some(response) = read_cached_games_list() else {
+        return Vec::new();
+    };
+
+    let mut candidates = Vec::new();
+    for game in response.games {
+        if let Some(slug) = &game.url_identifier {
+            candidates.push(CompletionCandidate::new(slug).help(Some(game.title.clone().into())));
+        }
+        candidates
+            .push(CompletionCandidate::new(game.id.to_string()).help(Some(game.title.into())));
+    }
+
+    candidates
+}
+
+// Only numeric ids of the cached developed games, for `--id`
+pub fn game_id_candidates() -> Vec<CompletionCandidate> {
+    let Some(response) = read_cached_games_list() else {
+        return Vec::new();
+    };
+
+    response
+        .games
+        .into_iter()
+        .map(|game| CompletionCandidate::new(game.id.to_string()).help(Some(game.title.into())))
+        .collect()
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    constants::PROJECT_CONFIG_FILE,
+    errors::common::CommonError,
+    transfer::{ByteRate, Concurrency},
+    utils::get_config_path,
+};
+
+// Optional settings from config.json in the config directory, command flags always take precedence
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    pub concurrency: Option<Concurrency>,
+    pub limit_rate: Option<ByteRate>,
+    pub games_cache_ttl: Option<u64>,
+    #[serde(flatten)]
+    pub network: NetworkConfig,
+}
+
+// Proxy, extra CA bundle and timeouts (in seconds), see `NetworkSettings`
+#[derive(Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkConfig {
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub ca_cert: Option<PathBuf>,
+    pub connect_timeout: Option<u64>,
+    pub read_timeout: Option<u64>,
+}
+
+impl NetworkConfig {
+    // Fields of self win, the missing ones are taken from other
+    pub fn or(self, other: NetworkConfig) -> NetworkConfig {
+        NetworkConfig {
+            proxy: self.proxy.or(other.proxy),
+            no_proxy: self.no_proxy.or(other.no_proxy),
+            ca_cert: self.ca_cert.or(other.ca_cert),
+            connect_timeout: self.connect_timeout.or(other.connect_timeout),
+            read_timeout: self.read_timeout.or(other.read_timeout),
+        }
+    }
+}
+
+pub fn load_config() -> Result<Config, CommonError> {
+    let config_path = get_config_path().join("config.json");
+
+    if !config_path.exists() {
+        return Ok(Config::default());
+    }
+
+    let content = fs::read_to_string(&config_path)?;
+    let parsed = serde_json::from_str::<Config>(&content)?;
+
+    Ok(parsed)
+}
+
+// Per project settings from clawdrop.json in the working directory, flags take precedence here too
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectConfig {
+    pub id: Option<u64>,
+    pub os: Option<String>,
+    pub exe: Option<String>,
+    pub path: Option<String>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+pub fn load_project_config() -> Result<ProjectConfig, CommonError> {
+    let config_path = Path::new(PROJECT_CONFIG_FILE);
+
+    if !config_path.exists() {
+        return Ok(ProjectConfig::default());
+    }
+
+    let content = fs::read_to_string(config_path)?;
+    let parsed = serde_json::from_str::<ProjectConfig>(&content)?;
+
+    Ok(parsed)
+}
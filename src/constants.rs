@@ -2,10 +2,29 @@ pub const VERIFY_API_KEY_URL: &str = "https://raccreativegames.com/api/auth/veri
 pub const AUTH_REQUEST_URL: &str = "https://raccreativegames.com/api/auth/cli-request";
 pub const AUTH_POLL_URL: &str = "https://raccreativegames.com/api/auth/cli-poll";
 pub const GAMES_LIST_URL: &str = "https://raccreativegames.com/api/games/developed";
+pub const GAME_DETAILS_URL: &str = "https://raccreativegames.com/api/games/{id}";
 pub const GAME_POST_URL: &str = "https://raccreativegames.com/api/game-news";
+pub const GAME_POST_IMAGE_URL: &str = "https://raccreativegames.com/api/game-news/images";
+pub const GAME_POST_ITEM_URL: &str = "https://raccreativegames.com/api/game-news/{id}";
+pub const GAME_POST_PUBLISH_URL: &str = "https://raccreativegames.com/api/game-news/{id}/publish";
 pub const REQUEST_UPLOAD_URL: &str =
     "https://raccreativegames.com/api/games/{id}/request-differential-upload";
 pub const VERIFY_UPLOAD_URL: &str =
     "https://raccreativegames.com/api/games/{id}/verify-differential-upload";
 pub const COMPLETE_PUSH_URL: &str = "https://raccreativegames.com/api/games/{id}/complete-push";
 pub const APP_NAME: &str = "Clawdrop";
+pub const PROJECT_CONFIG_FILE: &str = "clawdrop.json";
+
+// Seconds to wait for a connection and then for each read, unless configured
+pub const CONNECT_TIMEOUT: u64 = 10;
+pub const READ_TIMEOUT: u64 = 60;
+
+// Seconds the cached games list is used before asking the API again
+pub const GAMES_CACHE_TTL: u64 = 600;
+
+// Post covers as the site shows them: 16:9, at most 1280x720 and 2 MiB
+pub const COVER_WIDTH: u32 = 1280;
+pub const COVER_HEIGHT: u32 = 720;
+pub const COVER_MIN_WIDTH: u32 = 640;
+pub const COVER_MIN_HEIGHT: u32 = 360;
+pub const COVER_MAX_BYTES: u64 = 2 * 1024 * 1024;
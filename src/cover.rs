@@ -0,0 +1,351 @@
+use std::{fmt, fs, io::Cursor, path::Path, str::FromStr};
+
+use image::{
+    DynamicImage, ImageFormat, ImageReader, RgbImage,
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+};
+
+use crate::{
+    client::PostImage,
+    constants::{COVER_HEIGHT, COVER_MAX_BYTES, COVER_MIN_HEIGHT, COVER_MIN_WIDTH, COVER_WIDTH},
+    errors::post::PostError,
+    utils::format_bytes,
+};
+
+// Allowed difference with the 16:9 ratio before the cover is considered off
+const ASPECT_TOLERANCE: f64 = 0.01;
+const JPEG_QUALITIES: [u8; 6] = [90, 85, 75, 65, 55, 45];
+
+// What to do with covers that are not 16:9
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CoverFit {
+    #[default]
+    Crop,
+    Reject,
+}
+
+impl FromStr for CoverFit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "crop" => Ok(CoverFit::Crop),
+            "reject" => Ok(CoverFit::Reject),
+            _ => Err(format!("invalid fit '{}', expected crop or reject", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverFormat {
+    Jpeg,
+    Webp,
+}
+
+impl FromStr for CoverFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(CoverFormat::Jpeg),
+            "webp" => Ok(CoverFormat::Webp),
+            _ => Err(format!("invalid format '{}', expected jpeg or webp", s)),
+        }
+    }
+}
+
+impl fmt::Display for CoverFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoverFormat::Jpeg => write!(f, "JPEG"),
+            CoverFormat::Webp => write!(f, "WebP"),
+        }
+    }
+}
+
+// Cover ready to upload and the changes made to get there, for the user
+pub struct PreparedCover {
+    pub image: PostImage,
+    pub notes: Vec<String>,
+}
+
+// Decodes and checks a cover, then crops, downscales and re-encodes it only when the site would not take it as is.
+// Forcing a format always re-encodes
+pub fn prepare_cover(
+    path: &Path,
+    fit: CoverFit,
+    format: Option<CoverFormat>,
+) -> Result<PreparedCover, PostError> {
+    let invalid = |reason: String| PostError::InvalidCover {
+        path: path.display().to_string(),
+        reason,
+    };
+
+    let bytes = fs::read(path)?;
+    let reader = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|e| invalid(e.to_string()))?;
+    let source_format = reader
+        .format()
+        .ok_or_else(|| invalid("unknown image format".to_string()))?;
+    let mut image = reader
+        .decode()
+        .map_err(|e| invalid(format!("could not decode it: {}", e)))?;
+
+    let (width, height) = (image.width(), image.height());
+    if width < COVER_MIN_WIDTH || height < COVER_MIN_HEIGHT {
+        return Err(invalid(format!(
+            "{}x{} is smaller than the minimum {}x{}",
+            width, height, COVER_MIN_WIDTH, COVER_MIN_HEIGHT
+        )));
+    }
+
+    let mut notes = Vec::new();
+
+    let target_ratio = COVER_WIDTH as f64 / COVER_HEIGHT as f64;
+    let ratio = width as f64 / height as f64;
+    if (ratio - target_ratio).abs() / target_ratio > ASPECT_TOLERANCE {
+        if let CoverFit::Reject = fit {
+            return Err(invalid(format!(
+                "{}x{} is not 16:9, use --cover-fit crop to crop it",
+                width, height
+            )));
+        }
+
+        image = crop_to_ratio(&image, target_ratio);
+        notes.push(format!(
+            "cropped {}x{} to {}x{} (16:9)",
+            width,
+            height,
+            image.width(),
+            image.height()
+        ));
+    }
+
+    if image.width() > COVER_WIDTH {
+        let (before_w, before_h) = (image.width(), image.height());
+        image = image.resize_exact(COVER_WIDTH, COVER_HEIGHT, FilterType::Lanczos3);
+        notes.push(format!(
+            "resized {}x{} to {}x{}",
+            before_w, before_h, COVER_WIDTH, COVER_HEIGHT
+        ));
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "cover".to_string());
+
+    let web_ready = matches!(source_format, ImageFormat::Jpeg | ImageFormat::WebP);
+    let untouched = notes.is_empty() && format.is_none();
+    if untouched && web_ready && bytes.len() as u64 <= COVER_MAX_BYTES {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| stem.clone());
+
+        return Ok(PreparedCover {
+            image: PostImage {
+                file_name,
+                mime_type: source_format.to_mime_type().to_string(),
+                bytes,
+            },
+            notes,
+        });
+    }
+
+    // WebP stays WebP unless asked otherwise, everything else becomes JPEG
+    let target = format.unwrap_or(match source_format {
+        ImageFormat::WebP => CoverFormat::Webp,
+        _ => CoverFormat::Jpeg,
+    });
+
+    let mut encoded = None;
+    if target == CoverFormat::Webp {
+        let webp = encode_webp(&image).map_err(|e| invalid(e.to_string()))?;
+        if webp.len() as u64 <= COVER_MAX_BYTES {
+            encoded = Some((CoverFormat::Webp, webp, None));
+        } else if format == Some(CoverFormat::Webp) {
+            return Err(invalid(format!(
+                "lossless WebP is {}, over the {} limit, try --cover-format jpeg",
+                format_bytes(webp.len() as u64),
+                format_bytes(COVER_MAX_BYTES)
+            )));
+        }
+    }
+
+    if encoded.is_none() {
+        let rgb = flatten(&image);
+        for quality in JPEG_QUALITIES {
+            let jpeg = encode_jpeg(&rgb, quality).map_err(|e| invalid(e.to_string()))?;
+            if jpeg.len() as u64 <= COVER_MAX_BYTES {
+                encoded = Some((CoverFormat::Jpeg, jpeg, Some(quality)));
+                break;
+            }
+        }
+    }
+
+    let Some((format, bytes, quality)) = encoded else {
+        return Err(invalid(format!(
+            "could not get it under {} even at the lowest JPEG quality",
+            format_bytes(COVER_MAX_BYTES)
+        )));
+    };
+
+    notes.push(match quality {
+        Some(quality) => format!(
+            "encoded as {} quality {} ({})",
+            format,
+            quality,
+            format_bytes(bytes.len() as u64)
+        ),
+        None => format!(
+            "encoded as {} ({})",
+            format,
+            format_bytes(bytes.len() as u64)
+        ),
+    });
+
+    let (extension, mime_type) = match format {
+        CoverFormat::Jpeg => ("jpg", "image/jpeg"),
+        CoverFormat::Webp => ("webp", "image/webp"),
+    };
+
+    Ok(PreparedCover {
+        image: PostImage {
+            file_name: format!("{}.{}", stem, extension),
+            mime_type: mime_type.to_string(),
+            bytes,
+        },
+        notes,
+    })
+}
+
+// Largest centered area with the target ratio
+fn crop_to_ratio(image: &DynamicImage, ratio: f64) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+
+    if (width as f64 / height as f64) > ratio {
+        let new_width = (height as f64 * ratio).round() as u32;
+        image.crop_imm((width - new_width) / 2, 0, new_width, height)
+    } else {
+        let new_height = (width as f64 / ratio).round() as u32;
+        image.crop_imm(0, (height - new_height) / 2, width, new_height)
+    }
+}
+
+// JPEG has no alpha, transparent areas go on white instead of whatever color they hide
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+fn encode_jpeg(image: &RgbImage, quality: u8) -> image::ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, quality).encode_image(image)?;
+    Ok(bytes)
+}
+
+fn encode_webp(image: &DynamicImage) -> image::ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image
+        .to_rgba8()
+        .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbaImage};
+    use std::path::PathBuf;
+
+    fn write_image(name: &str, width: u32, height: u32, format: ImageFormat) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("clawdrop-cover-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, 90])
+        });
+        image.save_with_format(&path, format).unwrap();
+        path
+    }
+
+    fn size_of(image: &PostImage) -> (u32, u32) {
+        let decoded = image::load_from_memory(&image.bytes).unwrap();
+        (decoded.width(), decoded.height())
+    }
+
+    #[test]
+    fn crop_keeps_the_centered_area() {
+        // Each column and row remembers where it came from
+        let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(400, 100, |x, y| {
+            image::Rgba([(x / 2) as u8, y as u8, 0, 255])
+        }));
+        let wide = crop_to_ratio(&source, 16.0 / 9.0);
+        assert_eq!((wide.width(), wide.height()), (178, 100));
+        // (400 - 178) / 2 = 111 columns cut on the left
+        assert_eq!(wide.to_rgba8().get_pixel(0, 0).0[0], 111 / 2);
+
+        let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 200, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 255])
+        }));
+        let tall = crop_to_ratio(&source, 16.0 / 9.0);
+        assert_eq!((tall.width(), tall.height()), (160, 90));
+        assert_eq!(tall.to_rgba8().get_pixel(0, 0).0[1], 55);
+    }
+
+    #[test]
+    fn off_ratio_covers_are_cropped_or_rejected() {
+        let path = write_image("square.png", 800, 600, ImageFormat::Png);
+
+        let prepared = prepare_cover(&path, CoverFit::Crop, None).unwrap();
+        assert_eq!(size_of(&prepared.image), (800, 450));
+        assert_eq!(prepared.image.mime_type, "image/jpeg");
+        assert_eq!(prepared.image.file_name, "square.jpg");
+        assert_eq!(prepared.notes[0], "cropped 800x600 to 800x450 (16:9)");
+
+        let Err(PostError::InvalidCover { reason, .. }) =
+            prepare_cover(&path, CoverFit::Reject, None)
+        else {
+            panic!("expected the cover to be rejected");
+        };
+        assert!(reason.contains("is not 16:9"), "{}", reason);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn large_covers_are_downscaled_and_small_ones_rejected() {
+        let path = write_image("large.png", 1920, 1080, ImageFormat::Png);
+        let prepared = prepare_cover(&path, CoverFit::Crop, Some(CoverFormat::Webp)).unwrap();
+        assert_eq!(size_of(&prepared.image), (COVER_WIDTH, COVER_HEIGHT));
+        assert_eq!(prepared.image.mime_type, "image/webp");
+        assert_eq!(prepared.notes[0], "resized 1920x1080 to 1280x720");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let path = write_image("small.png", 320, 180, ImageFormat::Png);
+        assert!(matches!(
+            prepare_cover(&path, CoverFit::Crop, None),
+            Err(PostError::InvalidCover { .. })
+        ));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn ready_covers_are_sent_as_they_are() {
+        let path = write_image("ready.jpg", 1280, 720, ImageFormat::Jpeg);
+        let prepared = prepare_cover(&path, CoverFit::Reject, None).unwrap();
+        assert_eq!(prepared.image.bytes, fs::read(&path).unwrap());
+        assert_eq!(prepared.image.file_name, "ready.jpg");
+        assert!(prepared.notes.is_empty());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use aws_credential_types::provider::{ProvideCredentials, error::CredentialsError, future};
+use aws_sdk_s3::config::Credentials;
+use chrono::DateTime;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    client::{ExtraUploads, RaccreativeClient, RequestUploadResponse, TemporaryCredentials},
+    file_index::FileIndex,
+    pusher::{PushEvent, PushProgress},
+};
+
+// The S3 clients ask for new credentials this long before the current ones expire
+pub(crate) const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+// Sessions shorter than the margin above would otherwise refresh on every request
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum CredentialsKind {
+    Upload,
+    Delete,
+}
+
+// The request_upload answer of a push with the latest credentials. Both S3 clients read them from here,
+// and the first one to need new credentials asks the API again for both
+pub(crate) struct UploadSession {
+    client: RaccreativeClient,
+    game_id: u64,
+    os: String,
+    fileindex: Arc<FileIndex>,
+    progress: Arc<dyn PushProgress>,
+    state: Mutex<SessionState>,
+}
+
+struct SessionState {
+    response: RequestUploadResponse,
+    fetched_at: Instant,
+}
+
+impl UploadSession {
+    pub(crate) fn new(
+        client: RaccreativeClient,
+        game_id: u64,
+        os: String,
+        fileindex: Arc<FileIndex>,
+        progress: Arc<dyn PushProgress>,
+        response: RequestUploadResponse,
+    ) -> Self {
+        Self {
+            client,
+            game_id,
+            os,
+            fileindex,
+            progress,
+            state: Mutex::new(SessionState {
+                response,
+                fetched_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Upload id of the first request_upload, refreshes never replace it
+    pub(crate) async fn upload_id(&self) -> String {
+        self.state.lock().await.response.upload_id.clone()
+    }
+
+    // Presigned urls expire too, so they come from the latest answer as well
+    pub(crate) async fn extra_uploads(&self) -> ExtraUploads {
+        self.state.lock().await.response.extra_uploads.clone()
+    }
+
+    async fn credentials(&self, kind: CredentialsKind) -> Result<Credentials, CredentialsError> {
+        let mut state = self.state.lock().await;
+
+        let current = kind.select(&state.response);
+        let fresh = match expiry(current) {
+            Some(expiry) => expiry > SystemTime::now() + REFRESH_BEFORE_EXPIRY,
+            None => true,
+        };
+        // The other client may have refreshed them while this one waited for the lock
+        if fresh || state.fetched_at.elapsed() < MIN_REFRESH_INTERVAL {
+            return Ok(sdk_credentials(current));
+        }
+
+        info!(?kind, expiration = %current.expiration, "refreshing temporary credentials");
+        let response = self
+            .client
+            .request_upload(self.game_id, &self.os, &self.fileindex)
+            .await
+            .map_err(|e| {
+                warn!(error = %e, "credentials refresh failed");
+                CredentialsError::provider_error(e)
+            })?;
+
+        renew(&mut state.response, response).map_err(CredentialsError::provider_error)?;
+        state.fetched_at = Instant::now();
+        self.progress.report(PushEvent::CredentialsRefreshed);
+
+        Ok(sdk_credentials(kind.select(&state.response)))
+    }
+}
+
+// There is no endpoint that only renews credentials, so a refresh asks request_upload again and keeps
+// just the new secrets and presigned urls. The push stays on the upload id it was opened with: that
+// session holds the same fileindex and is the one verify_upload checks, the new one is left to expire
+fn renew(
+    current: &mut RequestUploadResponse,
+    refreshed: RequestUploadResponse,
+) -> Result<(), String> {
+    // Keys already being uploaded were built from the first answer
+    for kind in [CredentialsKind::Upload, CredentialsKind::Delete] {
+        let (old, new) = (kind.select(current), kind.select(&refreshed));
+        if old.bucket != new.bucket || old.prefix != new.prefix {
+            return Err(format!(
+                "the refreshed {:?} credentials point to {}/{} instead of {}/{}",
+                kind, new.bucket, new.prefix, old.bucket, old.prefix
+            ));
+        }
+    }
+
+    current.upload_credentials = refreshed.upload_credentials;
+    current.delete_credentials = refreshed.delete_credentials;
+    current.extra_uploads = refreshed.extra_uploads;
+    Ok(())
+}
+
+impl CredentialsKind {
+    fn select(self, response: &RequestUploadResponse) -> &TemporaryCredentials {
+        match self {
+            CredentialsKind::Upload => &response.upload_credentials,
+            CredentialsKind::Delete => &response.delete_credentials,
+        }
+    }
+}
+
+// Credentials provider of one S3 client, the SDK caches what it returns until shortly before expiry
+#[derive(Clone)]
+pub(crate) struct SessionCredentials {
+    session: Arc<UploadSession>,
+    kind: CredentialsKind,
+}
+
+impl SessionCredentials {
+    pub(crate) fn new(session: Arc<UploadSession>, kind: CredentialsKind) -> Self {
+        Self { session, kind }
+    }
+}
+
+// No secrets in Debug, the SDK prints providers in its own logs
+impl fmt::Debug for SessionCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionCredentials")
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+impl ProvideCredentials for SessionCredentials {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(self.session.credentials(self.kind))
+    }
+}
+
+fn sdk_credentials(credentials: &TemporaryCredentials) -> Credentials {
+    // Never the key id, secret or session token
+    debug!(
+        bucket = %credentials.bucket,
+        prefix = %credentials.prefix,
+        region = %credentials.region,
+        expiration = %credentials.expiration,
+        "temporary credentials"
+    );
+
+    Credentials::new(
+        credentials.access_key_id.clone(),
+        credentials.secret_access_key.clone(),
+        Some(credentials.session_token.clone()),
+        expiry(credentials),
+        "ApiTemporaryCredentials",
+    )
+}
+
+fn expiry(credentials: &TemporaryCredentials) -> Option<SystemTime> {
+    let expiration = DateTime::parse_from_rfc3339(&credentials.expiration).ok()?;
+    // A date before 1970 is long expired, not far in the future
+    let seconds = u64::try_from(expiration.timestamp()).unwrap_or(0);
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(upload_id: &str, key: &str, prefix: &str) -> RequestUploadResponse {
+        let credentials = serde_json::json!({
+            "accessKeyId": key,
+            "secretAccessKey": "secret",
+            "expiration": "2026-11-01T17:00:00Z",
+            "sessionToken": "token",
+            "bucket": "builds",
+            "prefix": prefix,
+            "region": "eu-west-1"
+        });
+        serde_json::from_value(serde_json::json!({
+            "uploadCredentials": credentials,
+            "deleteCredentials": credentials,
+            "extraUploads": {
+                "manifest": format!("https://s3/manifest?{}", key),
+                "fileindex": format!("https://s3/fileindex?{}", key)
+            },
+            "extraDownloads": { "fileindex": "https://cdn/fileindex.json" },
+            "originalZipName": null,
+            "uploadId": upload_id
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn refresh_keeps_the_upload_id() {
+        let mut current = response("first", "AKIA1", "games/7/windows");
+        renew(&mut current, response("second", "AKIA2", "games/7/windows")).unwrap();
+
+        assert_eq!(current.upload_id, "first");
+        assert_eq!(current.upload_credentials.access_key_id, "AKIA2");
+        assert_eq!(current.delete_credentials.access_key_id, "AKIA2");
+        assert_eq!(current.extra_uploads.manifest, "https://s3/manifest?AKIA2");
+    }
+
+    #[test]
+    fn refresh_to_another_prefix_is_refused() {
+        let mut current = response("first", "AKIA1", "games/7/windows");
+        let error =
+            renew(&mut current, response("second", "AKIA2", "games/8/windows")).unwrap_err();
+
+        assert!(error.contains("games/8/windows"), "{}", error);
+        assert_eq!(current.upload_credentials.access_key_id, "AKIA1");
+    }
+
+    #[test]
+    fn expiry_before_1970_is_expired() {
+        let mut credentials = response("id", "AKIA1", "p").upload_credentials;
+        assert_eq!(
+            expiry(&credentials),
+            Some(UNIX_EPOCH + Duration::from_secs(1_793_552_400))
+        );
+
+        credentials.expiration = "1969-12-31T23:00:00Z".to_string();
+        assert_eq!(expiry(&credentials), Some(UNIX_EPOCH));
+
+        credentials.expiration = "soon".to_string();
+        assert_eq!(expiry(&credentials), None);
+    }
+}
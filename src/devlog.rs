@@ -0,0 +1,391 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::BufReader,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use image::ImageReader;
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+use serde::Deserialize;
+
+use crate::errors::post::PostError;
+
+// Fields read from the YAML front matter, anything else in it is ignored
+#[derive(Deserialize, Default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub cover: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// Something in a devlog that would make the post fail or reference a missing file
+#[derive(Debug)]
+pub struct DevlogProblem {
+    pub line: usize,
+    pub target: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for DevlogProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {} ({})", self.line, self.target, self.reason)
+    }
+}
+
+// Markdown post with its front matter split out and every local image resolved
+pub struct Devlog {
+    pub front_matter: FrontMatter,
+    pub body: String,
+    pub cover: Option<PathBuf>,
+    // Unique local images in order of appearance, paths relative to the working directory
+    pub images: Vec<PathBuf>,
+    links: Vec<(Range<usize>, PathBuf)>,
+}
+
+impl Devlog {
+    // Reads and validates a devlog, every problem is reported at once before anything is sent
+    pub fn load(path: &Path) -> Result<Self, PostError> {
+        let content = fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+
+        let (front_matter, body, body_line) = split_front_matter(&content)?;
+        let mut problems = Vec::new();
+
+        let cover = match &front_matter.cover {
+            Some(cover) if !is_local(cover) => {
+                problems.push(DevlogProblem {
+                    line: front_matter_line(&content, "cover"),
+                    target: cover.clone(),
+                    reason: "cover must be a local file",
+                });
+                None
+            }
+            Some(cover) => check_local_image(
+                base_dir,
+                cover,
+                front_matter_line(&content, "cover"),
+                &mut problems,
+            ),
+            None => None,
+        };
+
+        let mut links = Vec::new();
+        let mut images: Vec<PathBuf> = Vec::new();
+
+        for (dest, range, line) in image_links(body) {
+            let line = body_line + line - 1;
+            let Some(range) = range else {
+                problems.push(DevlogProblem {
+                    line,
+                    target: dest,
+                    reason: "unsupported link syntax, use ![alt](path)",
+                });
+                continue;
+            };
+
+            if let Some(image) = check_local_image(base_dir, &dest, line, &mut problems) {
+                if !images.contains(&image) {
+                    images.push(image.clone());
+                }
+                links.push((range, image));
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(PostError::InvalidDevlog(problems));
+        }
+
+        Ok(Devlog {
+            front_matter,
+            body: body.to_string(),
+            cover,
+            images,
+            links,
+        })
+    }
+
+    // Body with every local image link pointing to its hosted URL
+    pub fn body_with_urls(&self, urls: &HashMap<PathBuf, String>) -> String {
+        let mut body = self.body.clone();
+
+        // Back to front so earlier ranges stay valid
+        let mut links: Vec<_> = self.links.iter().collect();
+        links.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        links.dedup_by_key(|(range, _)| range.start);
+
+        for (range, image) in links {
+            if let Some(url) = urls.get(image) {
+                body.replace_range(range.clone(), url);
+            }
+        }
+
+        body
+    }
+}
+
+// Returns the front matter, the body after it and the line the body starts on
+fn split_front_matter(content: &str) -> Result<(FrontMatter, &str, usize), PostError> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return Ok((FrontMatter::default(), content, 1));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let yaml = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            let front_matter = if yaml.trim().is_empty() {
+                FrontMatter::default()
+            } else {
+                serde_yaml::from_str(yaml).map_err(PostError::InvalidFrontMatter)?
+            };
+            let body_line = yaml.lines().count() + 3;
+            return Ok((front_matter, body, body_line));
+        }
+        offset += line.len();
+    }
+
+    Err(PostError::UnclosedFrontMatter)
+}
+
+// Local image links as (destination, byte range of the destination, line within the body).
+// Remote images are skipped, the range is None when the destination cannot be found in the source
+fn image_links(body: &str) -> Vec<(String, Option<Range<usize>>, usize)> {
+    let parser = Parser::new_ext(body, Options::all());
+    let refdefs: HashMap<String, Range<usize>> = parser
+        .reference_definitions()
+        .iter()
+        .map(|(label, def)| (label.to_lowercase(), def.span.clone()))
+        .collect();
+
+    let mut links = Vec::new();
+    let mut rewritten_refs = Vec::new();
+
+    for (event, range) in parser.into_offset_iter() {
+        let Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            id,
+            ..
+        }) = event
+        else {
+            continue;
+        };
+
+        if !is_local(&dest_url) {
+            continue;
+        }
+
+        let line = body[..range.start].matches('\n').count() + 1;
+        let dest_range = match link_type {
+            LinkType::Inline => {
+                let source = &body[range.clone()];
+                let start = source.rfind("](").map_or(0, |i| i + 2);
+                find_in(body, range.start + start..range.end, &dest_url)
+            }
+            LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut => {
+                let key = id.to_lowercase();
+                if rewritten_refs.contains(&key) {
+                    continue;
+                }
+                rewritten_refs.push(key.clone());
+
+                refdefs.get(&key).and_then(|span| {
+                    let label_end = body[span.clone()].find("]:").map_or(0, |i| i + 2);
+                    find_in(body, span.start + label_end..span.end, &dest_url)
+                })
+            }
+            _ => None,
+        };
+
+        links.push((dest_url.to_string(), dest_range, line));
+    }
+
+    links
+}
+
+// Line of a top level front matter key, for problem reports
+fn front_matter_line(content: &str, key: &str) -> usize {
+    content
+        .lines()
+        .position(|line| {
+            line.strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with(':'))
+        })
+        .map_or(1, |index| index + 1)
+}
+
+fn find_in(body: &str, within: Range<usize>, needle: &str) -> Option<Range<usize>> {
+    let start = within.start + body[within].find(needle)?;
+    Some(start..start + needle.len())
+}
+
+fn is_local(dest: &str) -> bool {
+    !(dest.is_empty()
+        || dest.starts_with('#')
+        || dest.starts_with("//")
+        || dest.starts_with("data:")
+        || dest.contains("://"))
+}
+
+// Resolves a local image against the devlog directory, recording why it is unusable if it is
+fn check_local_image(
+    base_dir: &Path,
+    target: &str,
+    line: usize,
+    problems: &mut Vec<DevlogProblem>,
+) -> Option<PathBuf> {
+    let path = base_dir.join(target);
+
+    let reason = if !path.is_file() {
+        "file not found"
+    } else {
+        match image_problem(&path) {
+            Some(reason) => reason,
+            None => return Some(path),
+        }
+    };
+
+    problems.push(DevlogProblem {
+        line,
+        target: target.to_string(),
+        reason,
+    });
+    None
+}
+
+// The content decides, a renamed or truncated file would otherwise only fail on the server
+fn image_problem(path: &Path) -> Option<&'static str> {
+    // Without the extension as a hint only the content picks the format
+    let reader = match fs::File::open(path)
+        .and_then(|file| ImageReader::new(BufReader::new(file)).with_guessed_format())
+    {
+        Ok(reader) => reader,
+        Err(_) => return Some("file could not be read"),
+    };
+    if reader.format().is_none() {
+        return Some("not a supported image");
+    }
+    reader.decode().err().map(|_| "image could not be decoded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn png() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbImage::new(2, 2)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn write_devlog(name: &str, markdown: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("clawdrop-devlog-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("img")).unwrap();
+        for (path, bytes) in files {
+            fs::write(dir.join(path), bytes).unwrap();
+        }
+        fs::write(dir.join("post.md"), markdown).unwrap();
+        dir
+    }
+
+    #[test]
+    fn image_links_are_rewritten_in_place() {
+        let png = png();
+        let markdown = "---\ntitle: Hello\ntags: [a]\n---\n\
+            Intro ![one](img/a.png) and ![again](img/a.png \"title\")\n\
+            ![remote](https://example.com/x.png) ![ref][shot]\n\n\
+            [shot]: img/b.png\n";
+        let dir = write_devlog(
+            "rewrite",
+            markdown,
+            &[("img/a.png", &png), ("img/b.png", &png)],
+        );
+
+        let devlog = Devlog::load(&dir.join("post.md")).unwrap();
+        assert_eq!(devlog.front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(
+            devlog.images,
+            [dir.join("img/a.png"), dir.join("img/b.png")]
+        );
+
+        let urls = HashMap::from([
+            (
+                dir.join("img/a.png"),
+                "https://cdn/a-long-name.png".to_string(),
+            ),
+            (dir.join("img/b.png"), "https://cdn/b".to_string()),
+        ]);
+        assert_eq!(
+            devlog.body_with_urls(&urls),
+            "Intro ![one](https://cdn/a-long-name.png) and ![again](https://cdn/a-long-name.png \"title\")\n\
+            ![remote](https://example.com/x.png) ![ref][shot]\n\n\
+            [shot]: https://cdn/b\n"
+        );
+
+        // Images without a URL keep their local link
+        assert_eq!(devlog.body_with_urls(&HashMap::new()), devlog.body);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn problems_are_reported_with_their_lines() {
+        let markdown = "---\ncover: https://example.com/c.png\n---\n\
+            ![missing](img/none.png)\n\
+            ![text](img/fake.png)\n\
+            ![cut](img/cut.png)\n";
+        let png = png();
+        let dir = write_devlog(
+            "problems",
+            markdown,
+            &[
+                ("img/fake.png", b"not an image"),
+                ("img/cut.png", &png[..png.len() / 2]),
+            ],
+        );
+
+        let Err(PostError::InvalidDevlog(problems)) = Devlog::load(&dir.join("post.md")) else {
+            panic!("expected devlog problems");
+        };
+        let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "line 2: https://example.com/c.png (cover must be a local file)",
+                "line 4: img/none.png (file not found)",
+                "line 5: img/fake.png (not a supported image)",
+                "line 6: img/cut.png (image could not be decoded)",
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unclosed_front_matter_is_an_error() {
+        assert!(matches!(
+            split_front_matter("---\ntitle: x\nbody"),
+            Err(PostError::UnclosedFrontMatter)
+        ));
+        let (front_matter, body, line) = split_front_matter("no front matter").unwrap();
+        assert!(front_matter.title.is_none());
+        assert_eq!((body, line), ("no front matter", 1));
+    }
+}
@@ -1,9 +1,27 @@
+pub mod api_key;
 pub mod auth;
+pub mod common;
 pub mod doctor;
-pub mod network;
-pub mod api_key;
+pub mod index;
 pub mod list;
-pub mod set;
+pub mod network;
 pub mod post;
-pub mod common;
-pub mod push;
\ No newline at end of file
+pub mod push;
+pub mod set;
+pub mod status;
+pub mod upgrade;
+pub mod verify;
+// Stable code, remediation hint and extra details of an error, for the CLI report and --output json.
+// Codes are never reused for another error, scripts and bug reports rely on them
+pub trait Diagnostic: std::error::Error {
+    fn code(&self) -> &'static str;
+
+    fn hint(&self) -> Option<&'static str> {
+        None
+    }
+
+    // Request ids and HTTP statuses as camelCase keys, printed with --verbose
+    fn details(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::errors::Diagnostic;
+
 #[derive(Debug)]
 pub enum ApiKeyError {
     MissingEnv,
@@ -8,7 +10,26 @@ pub enum ApiKeyError {
 impl fmt::Display for ApiKeyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ApiKeyError::MissingEnv => write!(f, "CLAWDROP_API_KEY is not configured. Please run 'clawdrop auth' first."),
+            ApiKeyError::MissingEnv => write!(
+                f,
+                "CLAWDROP_API_KEY is not configured. Please run 'clawdrop auth' first."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApiKeyError {}
+
+impl Diagnostic for ApiKeyError {
+    fn code(&self) -> &'static str {
+        match self {
+            ApiKeyError::MissingEnv => "CLW-KEY-001",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        match self {
+            ApiKeyError::MissingEnv => Some("run clawdrop auth, or set CLAWDROP_API_KEY in CI"),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,8 @@
 use std::fmt;
 use std::io;
 
-use crate::errors::common::CommonError;
+use crate::errors::network::NetworkError;
+use crate::errors::{Diagnostic, common::CommonError};
 use crate::impl_from;
 
 #[derive(Debug)]
@@ -44,3 +45,46 @@ impl std::error::Error for AuthError {
 
 impl_from!(io::Error => AuthError::Common : into);
 impl_from!(reqwest::Error => AuthError::Common : into);
+impl_from!(NetworkError => AuthError::Common : into);
+
+impl Diagnostic for AuthError {
+    fn code(&self) -> &'static str {
+        use AuthError::*;
+        match self {
+            Common(e) => e.code(),
+            InvalidConfigPath => "CLW-AUTH-001",
+            MissingApiKeyInResponse => "CLW-AUTH-002",
+            RequestExpired => "CLW-AUTH-003",
+            InvalidTokenResponse => "CLW-AUTH-004",
+            RequestTimeout => "CLW-AUTH-005",
+            InvalidProvidedKey => "CLW-AUTH-006",
+            UnknownStatus(_) => "CLW-AUTH-007",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use AuthError::*;
+        match self {
+            Common(e) => e.hint(),
+            InvalidConfigPath => {
+                Some("check that HOME (APPDATA on Windows) points to your user directory")
+            }
+            MissingApiKeyInResponse | InvalidTokenResponse | UnknownStatus(_) => {
+                Some("run clawdrop auth again")
+            }
+            RequestExpired | RequestTimeout => Some(
+                "run clawdrop auth again and approve the request in the browser within 4 minutes",
+            ),
+            InvalidProvidedKey => Some(
+                "copy the key again from your account settings or run clawdrop auth without --key",
+            ),
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            AuthError::Common(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
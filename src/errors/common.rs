@@ -1,14 +1,20 @@
 use std::fmt;
 use std::io;
 
-use crate::errors::network::NetworkError;
+use crate::errors::{Diagnostic, network::NetworkError};
+use crate::file_index::UnreadableFile;
 use crate::impl_from;
 
 #[derive(Debug)]
 pub enum CommonError {
     Network(NetworkError),
     Io(io::Error),
-    Json(serde_json::Error)
+    // A JSON file could not be parsed, responses of the API are NetworkError::Json
+    Json(serde_json::Error),
+    UnreadableFiles(Vec<UnreadableFile>),
+    NonUtf8Names(Vec<String>),
+    Archive(String),
+    Encode(serde_json::Error),
 }
 
 impl fmt::Display for CommonError {
@@ -18,6 +24,32 @@ impl fmt::Display for CommonError {
             Io(e) => write!(f, "Input/output error: {}", e),
             Network(e) => write!(f, "{}", e),
             Json(e) => write!(f, "JSON deserialization error: {}", e),
+            Archive(message) => write!(f, "Cannot read the build archive: {}", message),
+            Encode(e) => write!(f, "JSON serialization error: {}", e),
+            UnreadableFiles(files) => {
+                write!(f, "{} files or directories could not be read:", files.len())?;
+                for file in files.iter().take(10) {
+                    write!(f, "\n  {}", file)?;
+                }
+                if files.len() > 10 {
+                    write!(f, "\n  ...and {} more", files.len() - 10)?;
+                }
+                Ok(())
+            }
+            NonUtf8Names(names) => {
+                write!(
+                    f,
+                    "{} file names are not valid UTF-8 (shown with %XX for the invalid bytes):",
+                    names.len()
+                )?;
+                for name in names.iter().take(10) {
+                    write!(f, "\n  {}", name)?;
+                }
+                if names.len() > 10 {
+                    write!(f, "\n  ...and {} more", names.len() - 10)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -28,7 +60,8 @@ impl std::error::Error for CommonError {
         match self {
             Io(e) => Some(e),
             Network(e) => Some(e),
-            Json(e) => Some(e),
+            Json(e) | Encode(e) => Some(e),
+            UnreadableFiles(_) | NonUtf8Names(_) | Archive(_) => None,
         }
     }
 }
@@ -37,3 +70,44 @@ impl_from!(reqwest::Error => CommonError::Network : into);
 impl_from!(io::Error => CommonError::Io);
 impl_from!(serde_json::Error => CommonError::Json);
 impl_from!(NetworkError => CommonError::Network);
+
+impl Diagnostic for CommonError {
+    fn code(&self) -> &'static str {
+        use CommonError::*;
+        match self {
+            Network(e) => e.code(),
+            Io(_) => "CLW-COMMON-001",
+            Json(_) => "CLW-COMMON-002",
+            UnreadableFiles(_) => "CLW-COMMON-003",
+            NonUtf8Names(_) => "CLW-COMMON-004",
+            Archive(_) => "CLW-COMMON-005",
+            Encode(_) => "CLW-COMMON-006",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use CommonError::*;
+        match self {
+            Network(e) => e.hint(),
+            Io(_) => Some("check that the path exists and you can read and write it"),
+            Json(_) => Some("the file is not valid JSON, fix or delete it"),
+            UnreadableFiles(_) => {
+                Some("fix their permissions, or push --skip-unreadable to leave them out")
+            }
+            NonUtf8Names(_) => Some("rename them, or push --non-utf8 skip or --non-utf8 escape"),
+            Archive(_) => Some(
+                "use a .zip with stored or deflated entries, a .tar or a .tar.gz, or the extracted directory",
+            ),
+            Encode(_) => {
+                Some("this is a bug in clawdrop, please report it with the --verbose output")
+            }
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            CommonError::Network(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,30 +1,22 @@
 use std::{fmt, io};
 
-use crate::{errors::{api_key::ApiKeyError, common::CommonError}, impl_from};
+use crate::{
+    errors::{Diagnostic, api_key::ApiKeyError, common::CommonError},
+    impl_from,
+};
 
 #[derive(Debug)]
 pub enum DoctorError {
     Common(CommonError),
     ApiKey(ApiKeyError),
-    InvalidApiKey,
-    InternetUnavailable {
-        message: String,
-        source: Option<Box<dyn std::error::Error + Send + Sync>>,
-    },
+    ChecksFailed(usize),
 }
 
 impl fmt::Display for DoctorError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use DoctorError::*;
         match self {
-            InvalidApiKey => write!(f, "API key is invalid or expired."),
-            InternetUnavailable { message, source } => {
-                write!(f, "Internet check failed: {}", message)?;
-                if let Some(err) = source.as_ref() {
-                    write!(f, " (caused by: {})", err)?;
-                }
-                Ok(())
-            },
+            ChecksFailed(count) => write!(f, "{} check(s) failed, see the hints above", count),
             Common(e) => write!(f, "{}", e),
             ApiKey(e) => write!(f, "{}", e),
         }
@@ -36,7 +28,6 @@ impl std::error::Error for DoctorError {
         use DoctorError::*;
         match self {
             Common(e) => Some(e),
-            InternetUnavailable { source: Some(e), .. } => Some(&**e),
             _ => None,
         }
     }
@@ -44,4 +35,31 @@ impl std::error::Error for DoctorError {
 
 impl_from!(io::Error => DoctorError::Common : into);
 impl_from!(reqwest::Error => DoctorError::Common : into);
-impl_from!(ApiKeyError => DoctorError::ApiKey);
\ No newline at end of file
+impl_from!(ApiKeyError => DoctorError::ApiKey);
+
+impl Diagnostic for DoctorError {
+    fn code(&self) -> &'static str {
+        use DoctorError::*;
+        match self {
+            Common(e) => e.code(),
+            ApiKey(e) => e.code(),
+            ChecksFailed(_) => "CLW-DOCTOR-001",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use DoctorError::*;
+        match self {
+            Common(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            ChecksFailed(_) => Some("fix the failed checks and run clawdrop doctor again"),
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            DoctorError::Common(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
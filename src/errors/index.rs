@@ -0,0 +1,64 @@
+use std::{fmt, io};
+
+use crate::{
+    errors::{Diagnostic, common::CommonError},
+    file_index::SnapshotMismatch,
+    impl_from,
+};
+
+#[derive(Debug)]
+pub enum IndexError {
+    Common(CommonError),
+    Mismatch(Vec<SnapshotMismatch>),
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IndexError::*;
+        match self {
+            Common(e) => write!(f, "{}", e),
+            Mismatch(mismatches) => write!(
+                f,
+                "Build directory does not match the fileindex snapshot ({} differences)",
+                mismatches.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IndexError::Common(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl_from!(CommonError => IndexError::Common);
+impl_from!(io::Error => IndexError::Common : into);
+
+impl Diagnostic for IndexError {
+    fn code(&self) -> &'static str {
+        match self {
+            IndexError::Common(e) => e.code(),
+            IndexError::Mismatch(_) => "CLW-INDEX-001",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        match self {
+            IndexError::Common(e) => e.hint(),
+            IndexError::Mismatch(_) => Some(
+                "rebuild or re-export the game, or create a new snapshot with clawdrop index create",
+            ),
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            IndexError::Common(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,11 +1,17 @@
 use std::fmt;
 
-use crate::{errors::{api_key::ApiKeyError, network::NetworkError}, impl_from};
+use crate::{
+    errors::{Diagnostic, api_key::ApiKeyError, common::CommonError, network::NetworkError},
+    impl_from,
+};
 
 #[derive(Debug)]
 pub enum ListError {
+    Common(CommonError),
     Network(NetworkError),
     ApiKey(ApiKeyError),
+    NoIdSpecified,
+    GameNotFound(String),
 }
 
 impl fmt::Display for ListError {
@@ -14,6 +20,12 @@ impl fmt::Display for ListError {
         match self {
             Network(e) => write!(f, "{}", e),
             ApiKey(e) => write!(f, "{}", e),
+            Common(e) => write!(f, "{}", e),
+            NoIdSpecified => write!(
+                f,
+                "No game specified or target found, try clawdrop info <id> or clawdrop set <id>"
+            ),
+            GameNotFound(id) => write!(f, "Game '{}' is not among the games you develop.", id),
         }
     }
 }
@@ -22,6 +34,7 @@ impl std::error::Error for ListError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             ListError::Network(e) => Some(e),
+            ListError::Common(e) => Some(e),
             _ => None,
         }
     }
@@ -29,4 +42,40 @@ impl std::error::Error for ListError {
 
 impl_from!(NetworkError => ListError::Network);
 impl_from!(ApiKeyError => ListError::ApiKey);
-impl_from!(reqwest::Error => ListError::Network : into);
\ No newline at end of file
+impl_from!(CommonError => ListError::Common);
+impl_from!(reqwest::Error => ListError::Network : into);
+
+impl Diagnostic for ListError {
+    fn code(&self) -> &'static str {
+        use ListError::*;
+        match self {
+            Common(e) => e.code(),
+            Network(e) => e.code(),
+            ApiKey(e) => e.code(),
+            NoIdSpecified => "CLW-LIST-001",
+            GameNotFound(_) => "CLW-LIST-002",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use ListError::*;
+        match self {
+            Common(e) => e.hint(),
+            Network(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            NoIdSpecified => Some("pass a game id or slug, clawdrop list shows them"),
+            GameNotFound(_) => {
+                Some("check the id or slug with clawdrop list, or add --refresh if the game is new")
+            }
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        use ListError::*;
+        match self {
+            Common(e) => e.details(),
+            Network(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,5 +1,15 @@
-use std::fmt;
 use std::error::Error;
+use std::fmt;
+
+use crate::errors::Diagnostic;
+
+// What the server answered to a failed request, kept for --verbose and JSON reports
+#[derive(Debug)]
+pub struct ResponseInfo {
+    pub status: u16,
+    pub request_id: Option<String>,
+    pub body: String,
+}
 
 #[derive(Debug)]
 pub enum NetworkError {
@@ -11,7 +21,47 @@ pub enum NetworkError {
         message: String,
         source: reqwest::Error,
     },
-    InvalidApiKey
+    InvalidApiKey,
+    Unauthorized(ResponseInfo),
+    Forbidden(ResponseInfo),
+    NotFound(ResponseInfo),
+    PayloadTooLarge(ResponseInfo),
+    RateLimited {
+        retry_after: Option<u64>,
+        response: ResponseInfo,
+    },
+    Status(ResponseInfo),
+    InvalidSettings(String),
+}
+
+impl NetworkError {
+    // HTTP status of the failed request, when the server answered
+    pub fn status(&self) -> Option<u16> {
+        use NetworkError::*;
+        match self {
+            Http { source, .. } | Json { source, .. } => source.status().map(|s| s.as_u16()),
+            Unauthorized(response)
+            | Forbidden(response)
+            | NotFound(response)
+            | PayloadTooLarge(response)
+            | RateLimited { response, .. }
+            | Status(response) => Some(response.status),
+            InvalidApiKey | InvalidSettings(_) => None,
+        }
+    }
+
+    pub fn response(&self) -> Option<&ResponseInfo> {
+        use NetworkError::*;
+        match self {
+            Unauthorized(response)
+            | Forbidden(response)
+            | NotFound(response)
+            | PayloadTooLarge(response)
+            | RateLimited { response, .. }
+            | Status(response) => Some(response),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for NetworkError {
@@ -24,7 +74,36 @@ impl fmt::Display for NetworkError {
             Json { message, source } => {
                 write!(f, "{}: {}", message, source)
             }
-            InvalidApiKey => write!(f, "HTTP ERROR: API key might be invalid or expired. Run 'clawdrop auth' for a new one."),
+            InvalidApiKey | Unauthorized(_) => write!(
+                f,
+                "HTTP ERROR: API key might be invalid or expired. Run 'clawdrop auth' for a new one."
+            ),
+            Forbidden(_) => write!(
+                f,
+                "HTTP ERROR 403: You are not allowed to do this with your API key."
+            ),
+            NotFound(_) => write!(f, "HTTP ERROR 404: Not found in Raccreative Games."),
+            PayloadTooLarge(_) => write!(
+                f,
+                "HTTP ERROR 413: The request is too large for the server."
+            ),
+            RateLimited {
+                retry_after: Some(seconds),
+                ..
+            } => write!(
+                f,
+                "HTTP ERROR 429: Too many requests, try again in {} seconds.",
+                seconds
+            ),
+            RateLimited {
+                retry_after: None, ..
+            } => write!(f, "HTTP ERROR 429: Too many requests, try again later."),
+            Status(response) => write!(
+                f,
+                "Error in HTTP response: {}, {}",
+                response.status, response.body
+            ),
+            InvalidSettings(message) => write!(f, "Invalid network settings: {}", message),
         }
     }
 }
@@ -34,11 +113,63 @@ impl Error for NetworkError {
         match self {
             NetworkError::Http { source, .. } => Some(source),
             NetworkError::Json { source, .. } => Some(source),
-            _ => None
+            _ => None,
         }
     }
 }
 
+impl Diagnostic for NetworkError {
+    fn code(&self) -> &'static str {
+        use NetworkError::*;
+        match self {
+            Http { .. } => "CLW-NET-001",
+            Json { .. } => "CLW-NET-002",
+            InvalidApiKey => "CLW-NET-003",
+            Unauthorized(_) => "CLW-NET-004",
+            Forbidden(_) => "CLW-NET-005",
+            NotFound(_) => "CLW-NET-006",
+            PayloadTooLarge(_) => "CLW-NET-007",
+            RateLimited { .. } => "CLW-NET-008",
+            Status(_) => "CLW-NET-009",
+            InvalidSettings(_) => "CLW-NET-010",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use NetworkError::*;
+        Some(match self {
+            Http { source, .. } if source.is_timeout() => {
+                "the request timed out, check the connection or raise --read-timeout"
+            }
+            Http { .. } => "check your connection and proxy settings, clawdrop doctor tests them",
+            Json { .. } => {
+                "the server answered something unexpected, try again or update with clawdrop upgrade"
+            }
+            InvalidApiKey | Unauthorized(_) => "run clawdrop auth --force for a new API key",
+            Forbidden(_) => "make sure your account is a developer of the game",
+            NotFound(_) => "check the game id with clawdrop list",
+            PayloadTooLarge(_) => "send fewer or smaller files",
+            RateLimited { .. } => "wait a moment before trying again",
+            Status(response) if response.status >= 500 => {
+                "the server had a problem, try again in a few minutes"
+            }
+            Status(_) => "run again with --verbose and report the request id if it keeps failing",
+            InvalidSettings(_) => "fix --proxy, --ca-cert or the matching keys in config.json",
+        })
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        let mut details = Vec::new();
+        if let Some(status) = self.status() {
+            details.push(("httpStatus", status.to_string()));
+        }
+        if let Some(request_id) = self.response().and_then(|r| r.request_id.clone()) {
+            details.push(("requestId", request_id));
+        }
+        details
+    }
+}
+
 impl From<reqwest::Error> for NetworkError {
     fn from(e: reqwest::Error) -> Self {
         if e.is_decode() {
@@ -54,3 +185,54 @@ impl From<reqwest::Error> for NetworkError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::*;
+    use crate::errors::common::CommonError;
+
+    // Decode error of a 200 response whose body is not JSON
+    async fn decode_error() -> reqwest::Error {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let body = "<html>maintenance</html>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .get(format!("http://{}", addr))
+            .send()
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn response_decode_errors_are_not_file_errors() {
+        let error = CommonError::from(decode_error().await);
+
+        assert!(matches!(
+            error,
+            CommonError::Network(NetworkError::Json { .. })
+        ));
+        assert_eq!(error.code(), "CLW-NET-002");
+        assert_ne!(
+            error.hint(),
+            CommonError::Json(serde_json::from_str::<u8>("x").unwrap_err()).hint()
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use std::{fmt, io};
 
-use crate::{errors::{api_key::ApiKeyError, common::CommonError}, impl_from};
+use crate::{
+    devlog::DevlogProblem,
+    errors::{Diagnostic, api_key::ApiKeyError, common::CommonError, network::NetworkError},
+    impl_from,
+};
 
 #[derive(Debug)]
 pub enum PostError {
@@ -8,7 +12,17 @@ pub enum PostError {
     ApiKey(ApiKeyError),
     UnauthorizedToPost,
     NoIdSpecified,
-    ServerError { code: u16, message: String }
+    PostNotFound(String),
+    MissingTitle,
+    InvalidFrontMatter(serde_yaml::Error),
+    UnclosedFrontMatter,
+    InvalidDevlog(Vec<DevlogProblem>),
+    Git { command: String, message: String },
+    EmptyChangelog(String),
+    PublishAtInPast(String),
+    InvalidCover { path: String, reason: String },
+    FlushFailed(usize),
+    InvalidField { field: &'static str, reason: String },
 }
 
 impl fmt::Display for PostError {
@@ -17,9 +31,57 @@ impl fmt::Display for PostError {
         match self {
             Common(e) => write!(f, "{}", e),
             ApiKey(e) => write!(f, "{}", e),
-            ServerError { code, message } => write!(f, "Error in HTTP response: {}, {}", code, message),
-            UnauthorizedToPost => write!(f, "You don't have permissions to create a post for this game, are you the developer?"),
-            NoIdSpecified => write!(f, "No game ID specified or target found, try using --id or clawdrop set <id>"),
+            UnauthorizedToPost => write!(
+                f,
+                "You don't have permissions to create a post for this game, are you the developer?"
+            ),
+            NoIdSpecified => write!(
+                f,
+                "No game ID specified or target found, try using --id or clawdrop set <id>"
+            ),
+            MissingTitle => write!(
+                f,
+                "The post has no title, use --title or a title field in the front matter"
+            ),
+            InvalidFrontMatter(e) => write!(f, "Invalid YAML front matter: {}", e),
+            UnclosedFrontMatter => {
+                write!(f, "Front matter is not closed, add a '---' line after it")
+            }
+            InvalidDevlog(problems) => {
+                write!(f, "Devlog has problems, nothing was sent:")?;
+                for problem in problems.iter().take(10) {
+                    write!(f, "\n  {}", problem)?;
+                }
+                if problems.len() > 10 {
+                    write!(f, "\n  ...and {} more", problems.len() - 10)?;
+                }
+                Ok(())
+            }
+            Git { command, message } => write!(f, "git {} failed: {}", command, message),
+            EmptyChangelog(range) => write!(
+                f,
+                "No commits for the changelog in {}, check the range and --commit-types",
+                range
+            ),
+            PublishAtInPast(date) => write!(
+                f,
+                "--publish-at {} is in the past, leave it out to publish now",
+                date
+            ),
+            FlushFailed(count) => write!(
+                f,
+                "{} scheduled posts could not be published, they stay queued for the next flush",
+                count
+            ),
+            InvalidCover { path, reason } => {
+                write!(f, "Cover {} can not be used: {}", path, reason)
+            }
+            PostNotFound(post) => write!(
+                f,
+                "Post {} not found, check the slugs and IDs with clawdrop post list",
+                post
+            ),
+            InvalidField { field, reason } => write!(f, "Invalid post {}: {}", field, reason),
         }
     }
 }
@@ -28,6 +90,7 @@ impl std::error::Error for PostError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             PostError::Common(e) => Some(e),
+            PostError::InvalidFrontMatter(e) => Some(e),
             _ => None,
         }
     }
@@ -35,4 +98,73 @@ impl std::error::Error for PostError {
 
 impl_from!(ApiKeyError => PostError::ApiKey);
 impl_from!(reqwest::Error => PostError::Common : into);
-impl_from!(io::Error => PostError::Common : into);
\ No newline at end of file
+impl_from!(io::Error => PostError::Common : into);
+impl_from!(CommonError => PostError::Common);
+
+// A 403 from the post endpoints means the key owner is not a developer of the game
+impl From<NetworkError> for PostError {
+    fn from(e: NetworkError) -> Self {
+        match e {
+            NetworkError::Forbidden(_) => PostError::UnauthorizedToPost,
+            e => PostError::Common(e.into()),
+        }
+    }
+}
+
+impl Diagnostic for PostError {
+    fn code(&self) -> &'static str {
+        use PostError::*;
+        match self {
+            Common(e) => e.code(),
+            ApiKey(e) => e.code(),
+            UnauthorizedToPost => "CLW-POST-001",
+            NoIdSpecified => "CLW-POST-002",
+            PostNotFound(_) => "CLW-POST-003",
+            MissingTitle => "CLW-POST-004",
+            InvalidFrontMatter(_) => "CLW-POST-005",
+            UnclosedFrontMatter => "CLW-POST-006",
+            InvalidDevlog(_) => "CLW-POST-007",
+            Git { .. } => "CLW-POST-008",
+            EmptyChangelog(_) => "CLW-POST-009",
+            PublishAtInPast(_) => "CLW-POST-010",
+            InvalidCover { .. } => "CLW-POST-011",
+            FlushFailed(_) => "CLW-POST-012",
+            InvalidField { .. } => "CLW-POST-013",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use PostError::*;
+        match self {
+            Common(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            UnauthorizedToPost => {
+                Some("check the game with clawdrop status, only its developers can post")
+            }
+            NoIdSpecified => Some("pass --id or set a target with clawdrop set <id>"),
+            PostNotFound(_) => Some("list the posts of the game with clawdrop post list"),
+            MissingTitle => Some("add --title or a title: line to the front matter"),
+            InvalidFrontMatter(_) | UnclosedFrontMatter => {
+                Some("the front matter is the YAML between the first two '---' lines")
+            }
+            InvalidDevlog(_) => Some("fix the problems listed, nothing was posted"),
+            Git { .. } => Some("run it from a git repository with the range you passed"),
+            EmptyChangelog(_) => Some("widen the range or add more --commit-types"),
+            PublishAtInPast(_) => Some("use a future date or leave --publish-at out"),
+            InvalidCover { .. } => {
+                Some("use a 16:9 JPEG, PNG or WebP image of at least the minimum size")
+            }
+            FlushFailed(_) => Some("run clawdrop post flush again later"),
+            InvalidField { .. } => {
+                Some("fix it in the flags or the front matter, nothing was posted")
+            }
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            PostError::Common(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,12 +1,48 @@
-use std::{fmt, io};
+use std::{error::Error, fmt, io};
 
 use tokio::task::JoinError;
 
 use crate::{
-    errors::{api_key::ApiKeyError, common::CommonError, set::SetError},
+    errors::{
+        Diagnostic, api_key::ApiKeyError, common::CommonError, network::NetworkError, set::SetError,
+    },
+    file_index::SnapshotMismatch,
     impl_from,
+    paths::PathProblem,
 };
 
+// Failed S3 request, the SDK error stays as source for --verbose
+#[derive(Debug)]
+pub struct S3Failure {
+    pub message: String,
+    pub request_id: Option<String>,
+    pub extended_request_id: Option<String>,
+    pub source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl S3Failure {
+    pub fn new(message: impl Into<String>) -> Self {
+        S3Failure {
+            message: message.into(),
+            request_id: None,
+            extended_request_id: None,
+            source: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+// Obsolete remote file that could not be deleted
+#[derive(Clone, Debug)]
+pub struct DeleteFailure {
+    pub path: String,
+    pub message: String,
+}
+
 #[derive(Debug)]
 pub enum PushError {
     Common(CommonError),
@@ -23,9 +59,15 @@ pub enum PushError {
     UnauthorizedToUpload,
     FileSizeLimitReach,
     FileindexMismatch,
-    ServerError { code: u16, message: String },
     GameNotFound,
-    S3Error { message: String },
+    S3Error(S3Failure),
+    IndexMismatch(Vec<SnapshotMismatch>),
+    DeleteFailed(Vec<DeleteFailure>),
+    PushInProgress,
+    WatchFailed(notify::Error),
+    UnstableFiles(Vec<String>),
+    InvalidPaths(Vec<PathProblem>),
+    IndexWithArchive,
 }
 
 impl fmt::Display for PushError {
@@ -77,10 +119,67 @@ impl fmt::Display for PushError {
                 "Uploaded fileindex.json does not match with uploaded files, is fileindex.json correct or has been manipulated?"
             ),
             GameNotFound => write!(f, "The game you are trying to use was not found."),
-            ServerError { code, message } => {
-                write!(f, "Error in HTTP response: {}, {}", code, message)
+            S3Error(failure) => write!(f, "Error in S3: {}", failure.message),
+            IndexMismatch(mismatches) => {
+                write!(
+                    f,
+                    "Build does not match the --index snapshot, it will not be trusted:"
+                )?;
+                for mismatch in mismatches.iter().take(10) {
+                    write!(f, "\n  {}", mismatch)?;
+                }
+                if mismatches.len() > 10 {
+                    write!(f, "\n  ...and {} more", mismatches.len() - 10)?;
+                }
+                Ok(())
+            }
+            PushInProgress => write!(
+                f,
+                "Another clawdrop push of this game and OS is already running, wait for it to finish and try again."
+            ),
+            WatchFailed(e) => write!(f, "Could not watch the build directory: {}", e),
+            IndexWithArchive => write!(
+                f,
+                "A fileindex snapshot can only be used with a build directory, not an archive"
+            ),
+            InvalidPaths(problems) => {
+                write!(
+                    f,
+                    "{} paths would break the build on its platform:",
+                    problems.len()
+                )?;
+                for problem in problems.iter().take(10) {
+                    write!(f, "\n  {}", problem)?;
+                }
+                if problems.len() > 10 {
+                    write!(f, "\n  ...and {} more", problems.len() - 10)?;
+                }
+                Ok(())
+            }
+            UnstableFiles(paths) => {
+                write!(f, "{} files changed after they were indexed:", paths.len())?;
+                for path in paths.iter().take(10) {
+                    write!(f, "\n  {}", path)?;
+                }
+                if paths.len() > 10 {
+                    write!(f, "\n  ...and {} more", paths.len() - 10)?;
+                }
+                Ok(())
+            }
+            DeleteFailed(failures) => {
+                write!(
+                    f,
+                    "{} obsolete files could not be deleted from S3:",
+                    failures.len()
+                )?;
+                for failure in failures.iter().take(10) {
+                    write!(f, "\n  {} ({})", failure.path, failure.message)?;
+                }
+                if failures.len() > 10 {
+                    write!(f, "\n  ...and {} more", failures.len() - 10)?;
+                }
+                Ok(())
             }
-            S3Error { message } => write!(f, "Error in S3: {}", message),
         }
     }
 }
@@ -89,6 +188,11 @@ impl std::error::Error for PushError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             PushError::Common(e) => Some(e),
+            PushError::WatchFailed(e) => Some(e),
+            PushError::S3Error(failure) => failure
+                .source
+                .as_deref()
+                .map(|e| e as &(dyn Error + 'static)),
             _ => None,
         }
     }
@@ -97,7 +201,116 @@ impl std::error::Error for PushError {
 impl_from!(ApiKeyError => PushError::ApiKey);
 impl_from!(reqwest::Error => PushError::Common : into);
 impl_from!(io::Error => PushError::Common : into);
-impl_from!(serde_json::Error => PushError::Common : into);
 impl_from!(CommonError => PushError::Common);
 impl_from!(SetError => PushError::Set);
 impl_from!(JoinError => PushError::Join);
+impl_from!(notify::Error => PushError::WatchFailed);
+
+// What a status means for the upload endpoints, the rest stays a network error
+impl From<NetworkError> for PushError {
+    fn from(e: NetworkError) -> Self {
+        match e {
+            NetworkError::Forbidden(_) => PushError::UnauthorizedToUpload,
+            NetworkError::NotFound(_) => PushError::GameNotFound,
+            NetworkError::PayloadTooLarge(_) => PushError::FileSizeLimitReach,
+            e => PushError::Common(e.into()),
+        }
+    }
+}
+
+impl Diagnostic for PushError {
+    fn code(&self) -> &'static str {
+        use PushError::*;
+        match self {
+            Common(e) => e.code(),
+            ApiKey(e) => e.code(),
+            Set(e) => e.code(),
+            Join(_) => "CLW-PUSH-001",
+            InvalidShorthandFormat => "CLW-PUSH-002",
+            InvalidShorthandId => "CLW-PUSH-003",
+            InvalidOS => "CLW-PUSH-004",
+            MissingId => "CLW-PUSH-005",
+            MissingOS => "CLW-PUSH-006",
+            MissingExecutableName => "CLW-PUSH-007",
+            MissingExecutableFile => "CLW-PUSH-008",
+            UnauthorizedToUpload => "CLW-PUSH-009",
+            FileSizeLimitReach => "CLW-PUSH-010",
+            FileindexMismatch => "CLW-PUSH-011",
+            GameNotFound => "CLW-PUSH-012",
+            S3Error(_) => "CLW-PUSH-013",
+            IndexMismatch(_) => "CLW-PUSH-014",
+            DeleteFailed(_) => "CLW-PUSH-015",
+            PushInProgress => "CLW-PUSH-016",
+            WatchFailed(_) => "CLW-PUSH-017",
+            UnstableFiles(_) => "CLW-PUSH-018",
+            InvalidPaths(_) => "CLW-PUSH-019",
+            IndexWithArchive => "CLW-PUSH-020",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use PushError::*;
+        match self {
+            Common(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            Set(e) => e.hint(),
+            Join(_) => {
+                Some("this is a bug in clawdrop, please report it with the --verbose output")
+            }
+            InvalidShorthandFormat | InvalidShorthandId => {
+                Some("example: clawdrop push 123:windows/Game.exe:1.0.2")
+            }
+            InvalidOS | MissingOS => {
+                Some("pass --os windows, linux, mac or html, or set os in clawdrop.json")
+            }
+            MissingId => Some("pass --id, set id in clawdrop.json or run clawdrop set <id>"),
+            MissingExecutableName => Some("pass --exe or set exe in clawdrop.json"),
+            MissingExecutableFile => Some(
+                "the --exe path is relative to the build directory, check its spelling and case",
+            ),
+            UnauthorizedToUpload => {
+                Some("check the game with clawdrop status, only its developers can push")
+            }
+            FileSizeLimitReach => Some("make the build smaller or subscribe to Raccreative Star"),
+            FileindexMismatch => Some("push again, files may have changed during the upload"),
+            GameNotFound => Some("check the game id with clawdrop list"),
+            S3Error(_) => Some(
+                "push again, only the missing files are uploaded. Use --verbose for the S3 request ids",
+            ),
+            IndexMismatch(_) => Some(
+                "rebuild or re-export the game, or create a new snapshot with clawdrop index create",
+            ),
+            DeleteFailed(_) => Some(
+                "the new version is live, players never download the files listed, they only take up storage",
+            ),
+            PushInProgress => {
+                Some("wait for the other push to finish, the lock is released when it exits")
+            }
+            WatchFailed(_) => Some("check that the build directory exists and is readable"),
+            UnstableFiles(_) => Some("wait for the export to finish, or push with --wait-stable"),
+            InvalidPaths(_) => Some("rename or move these files, or ignore them with --ignore"),
+            IndexWithArchive => {
+                Some("push the archive without --index, it is indexed while it is read")
+            }
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        use PushError::*;
+        match self {
+            Common(e) => e.details(),
+            Set(e) => e.details(),
+            S3Error(failure) => {
+                let mut details = Vec::new();
+                if let Some(id) = &failure.request_id {
+                    details.push(("s3RequestId", id.clone()));
+                }
+                if let Some(id) = &failure.extended_request_id {
+                    details.push(("s3ExtendedRequestId", id.clone()));
+                }
+                details
+            }
+            _ => Vec::new(),
+        }
+    }
+}
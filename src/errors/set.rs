@@ -1,6 +1,9 @@
 use std::{fmt, io};
 
-use crate::{errors::{api_key::ApiKeyError, common::CommonError, network::NetworkError}, impl_from};
+use crate::{
+    errors::{Diagnostic, api_key::ApiKeyError, common::CommonError, network::NetworkError},
+    impl_from,
+};
 
 #[derive(Debug)]
 pub enum SetError {
@@ -17,7 +20,10 @@ impl fmt::Display for SetError {
         match self {
             Common(e) => write!(f, "{}", e),
             ApiKey(e) => write!(f, "{}", e),
-            UnauthorizedToSetGame => write!(f, "You don't have permissions to set this game, are you the developer?"),
+            UnauthorizedToSetGame => write!(
+                f,
+                "You don't have permissions to set this game, are you the developer?"
+            ),
             SerializationError => write!(f, "Could not serializate"),
             InvalidConfigPath => write!(f, "Invalid route for target file."),
         }
@@ -37,4 +43,39 @@ impl std::error::Error for SetError {
 impl_from!(NetworkError => SetError::Common : into);
 impl_from!(ApiKeyError => SetError::ApiKey);
 impl_from!(io::Error => SetError::Common : into);
-impl_from!(reqwest::Error => SetError::Common : into);
\ No newline at end of file
+impl_from!(reqwest::Error => SetError::Common : into);
+
+impl Diagnostic for SetError {
+    fn code(&self) -> &'static str {
+        use SetError::*;
+        match self {
+            Common(e) => e.code(),
+            ApiKey(e) => e.code(),
+            UnauthorizedToSetGame => "CLW-SET-001",
+            SerializationError => "CLW-SET-002",
+            InvalidConfigPath => "CLW-SET-003",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use SetError::*;
+        match self {
+            Common(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            UnauthorizedToSetGame => {
+                Some("check the id or slug with clawdrop list, or add --refresh if the game is new")
+            }
+            SerializationError => Some("run clawdrop set again"),
+            InvalidConfigPath => {
+                Some("check that HOME (APPDATA on Windows) points to your user directory")
+            }
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            SetError::Common(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
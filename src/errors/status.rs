@@ -0,0 +1,77 @@
+use std::{fmt, io};
+
+use crate::{
+    errors::{
+        Diagnostic, api_key::ApiKeyError, common::CommonError, network::NetworkError, set::SetError,
+    },
+    impl_from,
+};
+
+#[derive(Debug)]
+pub enum StatusError {
+    Common(CommonError),
+    ApiKey(ApiKeyError),
+    Set(SetError),
+    NoTarget,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use StatusError::*;
+        match self {
+            Common(e) => write!(f, "{}", e),
+            ApiKey(e) => write!(f, "{}", e),
+            Set(e) => write!(f, "{}", e),
+            NoTarget => write!(f, "No target game set, use clawdrop set <id>"),
+        }
+    }
+}
+
+impl std::error::Error for StatusError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use StatusError::*;
+        match self {
+            Common(e) => Some(e),
+            Set(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl_from!(CommonError => StatusError::Common);
+impl_from!(ApiKeyError => StatusError::ApiKey);
+impl_from!(SetError => StatusError::Set);
+impl_from!(NetworkError => StatusError::Common : into);
+impl_from!(io::Error => StatusError::Common : into);
+impl_from!(reqwest::Error => StatusError::Common : into);
+
+impl Diagnostic for StatusError {
+    fn code(&self) -> &'static str {
+        use StatusError::*;
+        match self {
+            Common(e) => e.code(),
+            ApiKey(e) => e.code(),
+            Set(e) => e.code(),
+            NoTarget => "CLW-STATUS-001",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use StatusError::*;
+        match self {
+            Common(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            Set(e) => e.hint(),
+            NoTarget => Some("choose the game to work on with clawdrop set <id>"),
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        use StatusError::*;
+        match self {
+            Common(e) => e.details(),
+            Set(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
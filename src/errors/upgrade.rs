@@ -0,0 +1,65 @@
+use std::{fmt, io};
+
+use tokio::task::JoinError;
+
+use crate::{
+    errors::{Diagnostic, common::CommonError},
+    impl_from,
+};
+
+#[derive(Debug)]
+pub enum UpgradeError {
+    Common(CommonError),
+    Update(self_update::errors::Error),
+    Join(JoinError),
+}
+
+impl fmt::Display for UpgradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use UpgradeError::*;
+        match self {
+            Common(e) => write!(f, "{}", e),
+            Update(e) => write!(f, "Could not update: {}", e),
+            Join(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for UpgradeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use UpgradeError::*;
+        match self {
+            Common(e) => Some(e),
+            Update(e) => Some(e),
+            Join(e) => Some(e),
+        }
+    }
+}
+
+impl Diagnostic for UpgradeError {
+    fn code(&self) -> &'static str {
+        use UpgradeError::*;
+        match self {
+            Common(e) => e.code(),
+            Update(_) => "CLW-UPGRADE-001",
+            Join(_) => "CLW-UPGRADE-002",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use UpgradeError::*;
+        match self {
+            Common(e) => e.hint(),
+            Update(_) => {
+                Some("check your connection to github.com, or download the release by hand")
+            }
+            Join(_) => {
+                Some("this is a bug in clawdrop, please report it with the --verbose output")
+            }
+        }
+    }
+}
+
+impl_from!(io::Error => UpgradeError::Common : into);
+impl_from!(self_update::errors::Error => UpgradeError::Update);
+impl_from!(JoinError => UpgradeError::Join);
@@ -0,0 +1,108 @@
+use std::{fmt, io};
+
+use crate::{
+    errors::{
+        Diagnostic, api_key::ApiKeyError, common::CommonError, network::NetworkError, set::SetError,
+    },
+    file_index::SnapshotMismatch,
+    impl_from,
+};
+
+#[derive(Debug)]
+pub enum VerifyError {
+    Common(CommonError),
+    ApiKey(ApiKeyError),
+    Set(SetError),
+    NoCheckSelected,
+    NoTarget,
+    NothingLive(String),
+    InvalidFileindexUrl(String),
+    Mismatch(Vec<SnapshotMismatch>),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use VerifyError::*;
+        match self {
+            Common(e) => write!(f, "{}", e),
+            ApiKey(e) => write!(f, "{}", e),
+            Set(e) => write!(f, "{}", e),
+            NoCheckSelected => write!(
+                f,
+                "Nothing to verify, pass --remote to check the live build"
+            ),
+            NoTarget => write!(
+                f,
+                "No game given, use --id or set a target with clawdrop set <id>"
+            ),
+            NothingLive(os) => write!(f, "There is no live build {}", os),
+            InvalidFileindexUrl(url) => {
+                write!(f, "The API returned an invalid fileindex URL: {}", url)
+            }
+            Mismatch(mismatches) => write!(
+                f,
+                "The live build does not match its fileindex ({} differences)",
+                mismatches.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use VerifyError::*;
+        match self {
+            Common(e) => Some(e),
+            Set(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl_from!(CommonError => VerifyError::Common);
+impl_from!(ApiKeyError => VerifyError::ApiKey);
+impl_from!(SetError => VerifyError::Set);
+impl_from!(NetworkError => VerifyError::Common : into);
+impl_from!(io::Error => VerifyError::Common : into);
+impl_from!(reqwest::Error => VerifyError::Common : into);
+
+impl Diagnostic for VerifyError {
+    fn code(&self) -> &'static str {
+        use VerifyError::*;
+        match self {
+            Common(e) => e.code(),
+            ApiKey(e) => e.code(),
+            Set(e) => e.code(),
+            NoTarget => "CLW-VERIFY-001",
+            NothingLive(_) => "CLW-VERIFY-002",
+            InvalidFileindexUrl(_) => "CLW-VERIFY-003",
+            Mismatch(_) => "CLW-VERIFY-004",
+            NoCheckSelected => "CLW-VERIFY-005",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        use VerifyError::*;
+        match self {
+            Common(e) => e.hint(),
+            ApiKey(e) => e.hint(),
+            Set(e) => e.hint(),
+            NoCheckSelected => {
+                Some("clawdrop index verify checks a local build against a fileindex snapshot")
+            }
+            NoTarget => Some("pass --id or choose the game with clawdrop set <id>"),
+            NothingLive(_) => Some("push a build first, or check --os"),
+            InvalidFileindexUrl(_) => Some("try again later, and report it if it keeps happening"),
+            Mismatch(_) => Some("push again with --force to upload every file"),
+        }
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        use VerifyError::*;
+        match self {
+            Common(e) => e.details(),
+            Set(e) => e.details(),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,16 +1,25 @@
 use std::{
-    collections::HashMap,
-    fs,
+    collections::{HashMap, HashSet},
+    fmt, fs,
     io::{BufReader, Read},
-    path::Path,
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
+use base64::{Engine, prelude::BASE64_STANDARD};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use mime_guess::MimeGuess;
+use rand::seq::IndexedRandom;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::{
+    archive::{ArchiveEntry, ArchiveKind, ArchiveReader, list_files},
+    errors::common::CommonError,
+    paths::escape_non_utf8,
+};
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct FileEntry {
@@ -18,26 +27,93 @@ pub struct FileEntry {
     pub size: u64,
     pub hash: String,
     pub content_type: String,
+    // When the file (or its archive) was indexed, push checks it again before uploading. Never sent to the API
+    #[serde(skip)]
+    pub(crate) modified: Option<SystemTime>,
+    // Where the content is read from when it is not at `path` in the build directory. Never sent to the API
+    #[serde(skip)]
+    pub(crate) source: Option<FileSource>,
+}
+
+#[derive(Clone, Debug)]
+pub enum FileSource {
+    // The name on disk of an escaped non-UTF-8 `path`
+    Disk(PathBuf),
+    Archive(ArchiveEntry),
+}
+
+impl FileEntry {
+    // An entry read from `path` in the build directory, without a recorded modification time
+    pub fn new(path: String, size: u64, hash: String, content_type: String) -> Self {
+        FileEntry {
+            path,
+            size,
+            hash,
+            content_type,
+            modified: None,
+            source: None,
+        }
+    }
+
+    // The hex SHA-256 in the base64 form S3 uses for x-amz-checksum-sha256
+    pub fn checksum_sha256(&self) -> Option<String> {
+        if self.hash.len() != 64 {
+            return None;
+        }
+        let bytes = (0..self.hash.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(self.hash.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        Some(BASE64_STANDARD.encode(bytes))
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct FileIndex {
     pub files: Vec<FileEntry>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+// A single difference found when checking a build directory against a fileindex snapshot
+#[derive(Debug)]
+pub struct SnapshotMismatch {
+    pub path: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for SnapshotMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path, self.reason)
+    }
+}
+
+// A file or directory that could not be read while indexing, with the reason
+#[derive(Debug)]
+pub struct UnreadableFile {
+    pub path: String,
+    pub error: std::io::Error,
+}
+
+impl fmt::Display for UnreadableFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path, self.error)
+    }
+}
+
+// What scan_fileindex found, callers decide what is fatal
+pub struct Scan {
+    pub index: FileIndex,
+    pub unreadable: Vec<UnreadableFile>,
+    // Names that are not valid UTF-8, %XX escaped. Only in the index when escaping was asked
+    pub non_utf8: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct FileChanges {
     pub new_files: Vec<FileEntry>,
     pub modified_files: Vec<FileEntry>,
     pub deleted_files: Vec<FileEntry>,
 }
 
-impl Default for FileIndex {
-    fn default() -> Self {
-        FileIndex { files: Vec::new() }
-    }
-}
-
 impl FileChanges {
     pub fn to_upload(&self, force: bool, local: &FileIndex) -> Vec<FileEntry> {
         if force {
@@ -50,100 +126,493 @@ impl FileChanges {
     }
 }
 
+// Fails with every file that could not be read, a build must never go out with some missing
 pub fn generate_fileindex<P: AsRef<Path>>(
     dir: P,
     ignore_patterns: &[String],
-) -> Result<FileIndex, std::io::Error> {
+) -> Result<FileIndex, CommonError> {
+    let scan = scan_fileindex(dir, ignore_patterns, false)?;
+    if !scan.unreadable.is_empty() {
+        return Err(CommonError::UnreadableFiles(scan.unreadable));
+    }
+    if !scan.non_utf8.is_empty() {
+        return Err(CommonError::NonUtf8Names(scan.non_utf8));
+    }
+    Ok(scan.index)
+}
+
+// Indexes what can be read and returns the rest, for callers that may go on without them.
+// `dir` may also be a .zip, .tar or .tar.gz of the build
+pub fn scan_fileindex<P: AsRef<Path>>(
+    dir: P,
+    ignore_patterns: &[String],
+    escape_non_utf8: bool,
+) -> Result<Scan, CommonError> {
     let base_path = dir.as_ref();
+    let globset = build_ignore_set(ignore_patterns)?;
 
-    let mut globset_builder = GlobSetBuilder::new();
-    for pattern in ignore_patterns {
-        globset_builder.add(Glob::new(pattern).map_err(|e| {
-            std::io::Error::new(std::io::ErrorKind::Other, format!("Invalid glob: {}", e))
-        })?);
+    if let Some(kind) = ArchiveKind::detect(base_path)? {
+        return scan_archive(base_path, kind, &globset, escape_non_utf8);
     }
 
-    let globset = globset_builder.build().map_err(|e| {
-        std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("GlobSet build error: {}", e),
-        )
-    })?;
-
-    let mut paths = Vec::new();
-    collect_paths(base_path, base_path, &mut paths, &globset)?;
+    let mut walk = Walk {
+        escape_non_utf8,
+        ..Walk::default()
+    };
+    collect_paths(base_path, base_path, &mut walk, &globset)?;
+    let Walk {
+        paths,
+        mut unreadable,
+        mut non_utf8,
+        ..
+    } = walk;
 
-    let entries: Vec<FileEntry> = paths
+    let results: Vec<Result<FileEntry, UnreadableFile>> = paths
         .into_par_iter()
-        .filter_map(|(path, relative_path, metadata)| {
-            let size = metadata.len();
-            let file = fs::File::open(&path).ok()?;
-            let mut reader = BufReader::new(file);
-            let mut hasher = Sha256::new();
-            let mut buffer = vec![0u8; 262144];
-
-            while let Ok(n) = reader.read(&mut buffer) {
-                if n == 0 {
-                    break;
+        .map(|(path, relative_path, metadata, escaped)| {
+            let hash = match hash_file(&path) {
+                Ok(hash) => hash,
+                Err(error) => {
+                    return Err(UnreadableFile {
+                        path: relative_path,
+                        error,
+                    });
                 }
-                hasher.update(&buffer[..n]);
-            }
-
-            let hash = format!("{:x}", hasher.finalize());
+            };
             let mime_type = MimeGuess::from_path(&path).first_or_octet_stream();
             let content_type = mime_type.essence_str().to_string();
 
-            Some(FileEntry {
+            Ok(FileEntry {
                 path: relative_path,
-                size,
+                size: metadata.len(),
                 hash,
                 content_type,
+                modified: metadata.modified().ok(),
+                source: escaped.then_some(FileSource::Disk(path)),
             })
         })
         .collect();
 
-    let mut sorted = entries;
-    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut files = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Ok(entry) => files.push(entry),
+            Err(file) => unreadable.push(file),
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    unreadable.sort_by(|a, b| a.path.cmp(&b.path));
+    non_utf8.sort();
+
+    Ok(Scan {
+        index: FileIndex { files },
+        unreadable,
+        non_utf8,
+    })
+}
+
+// Paths, sizes and hashes as the extracted archive would give them, so pushing the archive and
+// pushing the directory compare equal. Ignored entries are left out before they are read
+fn scan_archive(
+    path: &Path,
+    kind: ArchiveKind,
+    ignore_set: &GlobSet,
+    escape: bool,
+) -> Result<Scan, CommonError> {
+    let modified = fs::metadata(path)?.modified().ok();
+    let reader = ArchiveReader::new(path, kind);
+
+    let mut entries = Vec::new();
+    let mut non_utf8 = Vec::new();
+    for file in list_files(path, kind)? {
+        let (relative_path, escaped) = match String::from_utf8(file.name) {
+            Ok(name) => (name, false),
+            Err(e) => (escape_non_utf8(e.as_bytes()), true),
+        };
+
+        if ignore_set.is_match(&relative_path) {
+            continue;
+        }
+        if escaped {
+            non_utf8.push(relative_path.clone());
+            if !escape {
+                continue;
+            }
+        }
+        entries.push((relative_path, file.entry));
+    }
+
+    let hash_entry = |(relative_path, entry): (String, ArchiveEntry)| {
+        let mut hasher = Sha256::new();
+        let read = reader.read_entry(&entry, |data| {
+            hasher.update(data);
+            true
+        });
+        if let Err(error) = read {
+            return Err(UnreadableFile {
+                path: relative_path,
+                error,
+            });
+        }
+
+        let mime_type = MimeGuess::from_path(&relative_path).first_or_octet_stream();
+        Ok(FileEntry {
+            content_type: mime_type.essence_str().to_string(),
+            path: relative_path,
+            size: entry.size(),
+            hash: format!("{:x}", hasher.finalize()),
+            modified,
+            source: Some(FileSource::Archive(entry)),
+        })
+    };
+
+    // A tar.gz is read front to back, zip and tar entries can be read in parallel
+    let results: Vec<Result<FileEntry, UnreadableFile>> = if reader.is_sequential() {
+        entries.into_iter().map(hash_entry).collect()
+    } else {
+        entries.into_par_iter().map(hash_entry).collect()
+    };
+
+    let mut files = Vec::with_capacity(results.len());
+    let mut unreadable = Vec::new();
+    for result in results {
+        match result {
+            Ok(entry) => files.push(entry),
+            Err(file) => unreadable.push(file),
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    unreadable.sort_by(|a, b| a.path.cmp(&b.path));
+    non_utf8.sort();
+
+    Ok(Scan {
+        index: FileIndex { files },
+        unreadable,
+        non_utf8,
+    })
+}
+
+// Path, size and mtime of every file, cheap enough to poll while an export is still being written
+pub fn tree_state<P: AsRef<Path>>(
+    dir: P,
+    ignore_patterns: &[String],
+) -> Result<Vec<(String, u64, Option<SystemTime>)>, std::io::Error> {
+    let base_path = dir.as_ref();
+    let globset = build_ignore_set(ignore_patterns)?;
+
+    // An archive build is done once the archive itself stops changing
+    if base_path.is_file() {
+        let metadata = fs::metadata(base_path)?;
+        return Ok(vec![(
+            String::new(),
+            metadata.len(),
+            metadata.modified().ok(),
+        )]);
+    }
+
+    // Unreadable files cannot change size either, indexing reports them afterwards
+    let mut walk = Walk {
+        escape_non_utf8: true,
+        ..Walk::default()
+    };
+    collect_paths(base_path, base_path, &mut walk, &globset)?;
+
+    let mut state: Vec<_> = walk
+        .paths
+        .into_iter()
+        .map(|(_, relative_path, metadata, _)| {
+            (relative_path, metadata.len(), metadata.modified().ok())
+        })
+        .collect();
+    state.sort();
+    Ok(state)
+}
+
+pub fn read_fileindex<P: AsRef<Path>>(path: P) -> Result<FileIndex, std::io::Error> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid fileindex: {}", e),
+        )
+    })
+}
+
+pub fn write_fileindex<P: AsRef<Path>>(path: P, index: &FileIndex) -> Result<(), std::io::Error> {
+    let json = serde_json::to_vec_pretty(index).map_err(std::io::Error::other)?;
+    fs::write(path, json)
+}
+
+// Hashes the whole directory again and reports every difference with the snapshot
+pub fn verify_fileindex<P: AsRef<Path>>(
+    dir: P,
+    snapshot: &FileIndex,
+    ignore_patterns: &[String],
+) -> Result<Vec<SnapshotMismatch>, CommonError> {
+    let local = generate_fileindex(dir, ignore_patterns)?;
+    let changes = compare_fileindex(&local, snapshot, &None);
+
+    let mut mismatches = Vec::new();
+    for entry in changes.new_files {
+        mismatches.push(SnapshotMismatch {
+            path: entry.path,
+            reason: "not in fileindex",
+        });
+    }
+    for entry in changes.modified_files {
+        mismatches.push(SnapshotMismatch {
+            path: entry.path,
+            reason: "content differs",
+        });
+    }
+    for entry in changes.deleted_files {
+        mismatches.push(SnapshotMismatch {
+            path: entry.path,
+            reason: "missing in directory",
+        });
+    }
+
+    mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(mismatches)
+}
+
+// Cheap check for a trusted snapshot: paths and sizes must match exactly,
+// but only `sample_size` random files are hashed again
+pub fn verify_fileindex_sample<P: AsRef<Path>>(
+    dir: P,
+    snapshot: &FileIndex,
+    ignore_patterns: &[String],
+    sample_size: usize,
+) -> Result<Vec<SnapshotMismatch>, std::io::Error> {
+    let base_path = dir.as_ref();
+    let globset = build_ignore_set(ignore_patterns)?;
+
+    // Same walk as scan_fileindex, so every mismatch says why
+    let mut walk = Walk {
+        escape_non_utf8: true,
+        ..Walk::default()
+    };
+    collect_paths(base_path, base_path, &mut walk, &globset)?;
+
+    let local: HashMap<_, _> = walk
+        .paths
+        .iter()
+        .map(|(path, relative_path, metadata, _)| (relative_path.as_str(), (path, metadata.len())))
+        .collect();
+    let snapshot_paths: HashSet<_> = snapshot.files.iter().map(|f| f.path.as_str()).collect();
+    // Files inside an unreadable directory are unreadable too, not missing
+    let unreadable = |path: &str| {
+        walk.unreadable.iter().any(|file| {
+            file.path == path || (file.path.ends_with('/') && path.starts_with(&file.path))
+        })
+    };
+
+    let mut mismatches = Vec::new();
+    for file in &walk.unreadable {
+        mismatches.push(SnapshotMismatch {
+            path: file.path.clone(),
+            reason: "unreadable",
+        });
+    }
+    for entry in &snapshot.files {
+        match local.get(entry.path.as_str()) {
+            None if unreadable(&entry.path) => (),
+            None => mismatches.push(SnapshotMismatch {
+                path: entry.path.clone(),
+                reason: "missing in directory",
+            }),
+            Some((_, size)) if *size != entry.size => mismatches.push(SnapshotMismatch {
+                path: entry.path.clone(),
+                reason: "size differs",
+            }),
+            _ => (),
+        }
+    }
+    for (_, relative_path, _, escaped) in &walk.paths {
+        if !snapshot_paths.contains(relative_path.as_str()) {
+            mismatches.push(SnapshotMismatch {
+                path: relative_path.clone(),
+                reason: if *escaped {
+                    "name is not valid UTF-8"
+                } else {
+                    "not in fileindex"
+                },
+            });
+        }
+    }
+
+    if !mismatches.is_empty() {
+        mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+        return Ok(mismatches);
+    }
+
+    let sample: Vec<&FileEntry> = snapshot
+        .files
+        .choose_multiple(&mut rand::rng(), sample_size)
+        .collect();
+
+    // Every snapshot path was found above, escaped names are read from their real name
+    let mut sampled: Vec<SnapshotMismatch> = sample
+        .into_par_iter()
+        .filter_map(|entry| {
+            let hash = match local.get(entry.path.as_str()) {
+                Some((path, _)) => hash_file(path),
+                None => hash_file(base_path.join(&entry.path)),
+            };
+            match hash {
+                Ok(hash) if hash == entry.hash => None,
+                Ok(_) => Some(SnapshotMismatch {
+                    path: entry.path.clone(),
+                    reason: "content differs",
+                }),
+                Err(_) => Some(SnapshotMismatch {
+                    path: entry.path.clone(),
+                    reason: "unreadable",
+                }),
+            }
+        })
+        .collect();
 
-    Ok(FileIndex { files: sorted })
+    sampled.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(sampled)
+}
+
+// Whether indexing leaves out this relative path, itself or through one of its directories
+pub fn is_ignored(ignore_set: &GlobSet, relative_path: &str) -> bool {
+    relative_path
+        .match_indices('/')
+        .map(|(i, _)| &relative_path[..i])
+        .chain([relative_path])
+        .any(|path| ignore_set.is_match(path))
+}
+
+pub fn build_ignore_set(ignore_patterns: &[String]) -> Result<GlobSet, std::io::Error> {
+    let mut globset_builder = GlobSetBuilder::new();
+    for pattern in ignore_patterns {
+        globset_builder.add(
+            Glob::new(pattern)
+                .map_err(|e| std::io::Error::other(format!("Invalid glob: {}", e)))?,
+        );
+    }
+
+    globset_builder
+        .build()
+        .map_err(|e| std::io::Error::other(format!("GlobSet build error: {}", e)))
+}
+
+fn hash_file<P: AsRef<Path>>(path: P) -> Result<String, std::io::Error> {
+    let file = fs::File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 262144];
+
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Files found by collect_paths, with the relative path and whether it had to be escaped
+#[derive(Default)]
+struct Walk {
+    escape_non_utf8: bool,
+    paths: Vec<(PathBuf, String, fs::Metadata, bool)>,
+    unreadable: Vec<UnreadableFile>,
+    non_utf8: Vec<String>,
 }
 
 fn collect_paths(
     dir: &Path,
     base: &Path,
-    paths: &mut Vec<(std::path::PathBuf, String, std::fs::Metadata)>,
+    walk: &mut Walk,
     ignore_set: &GlobSet,
 ) -> Result<(), std::io::Error> {
     let mut stack = vec![dir.to_path_buf()];
     while let Some(current_dir) = stack.pop() {
-        for entry in fs::read_dir(&current_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let metadata = entry.metadata()?;
+        // Only the build directory itself is fatal, below it everything is collected
+        let entries = match fs::read_dir(&current_dir) {
+            Ok(entries) => entries,
+            Err(e) if current_dir == dir => return Err(e),
+            Err(error) => {
+                walk.unreadable.push(UnreadableFile {
+                    path: format!("{}/", to_relative(&current_dir, base).0),
+                    error,
+                });
+                continue;
+            }
+        };
 
-            let relative_path = path
-                .strip_prefix(base)
-                .unwrap_or(&path)
-                .to_str()
-                .ok_or_else(|| {
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid path encoding")
-                })?
-                .replace('\\', "/");
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    walk.unreadable.push(UnreadableFile {
+                        path: format!("{}/", to_relative(&current_dir, base).0),
+                        error,
+                    });
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let (relative_path, escaped) = to_relative(&path, base);
 
             if ignore_set.is_match(&relative_path) {
                 continue;
             }
 
+            // Reported once, a skipped directory is not walked
+            if escaped
+                && !walk
+                    .non_utf8
+                    .iter()
+                    .any(|parent| relative_path.starts_with(&format!("{}/", parent)))
+            {
+                walk.non_utf8.push(relative_path.clone());
+            }
+            if escaped && !walk.escape_non_utf8 {
+                continue;
+            }
+
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    walk.unreadable.push(UnreadableFile {
+                        path: relative_path,
+                        error,
+                    });
+                    continue;
+                }
+            };
+
             if metadata.is_dir() {
                 stack.push(path);
             } else if metadata.is_file() {
-                paths.push((path, relative_path, metadata));
+                walk.paths.push((path, relative_path, metadata, escaped));
             }
         }
     }
     Ok(())
 }
 
+// Relative path with / separators, and whether non-UTF-8 bytes had to be escaped
+fn to_relative(path: &Path, base: &Path) -> (String, bool) {
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    match relative.to_str() {
+        Some(relative) => (relative.replace('\\', "/"), false),
+        None => (
+            escape_non_utf8(relative.as_os_str().as_encoded_bytes()).replace('\\', "/"),
+            true,
+        ),
+    }
+}
+
 pub fn compare_fileindex(
     local: &FileIndex,
     remote: &FileIndex,
@@ -173,7 +642,7 @@ pub fn compare_fileindex(
         let is_manifest = remote_file.path.ends_with("manifest.json");
         let is_original_zip = original_zip_name
             .as_ref()
-            .map_or(false, |name| remote_file.path.ends_with(name));
+            .is_some_and(|name| remote_file.path.ends_with(name));
 
         if !local_map.contains_key(&remote_file.path) && !is_manifest && !is_original_zip {
             deleted_files.push(remote_file.clone());
@@ -186,3 +655,184 @@ pub fn compare_fileindex(
         deleted_files,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fresh build directory under the system temp dir, one per test
+    fn build_dir(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("clawdrop-index-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn reasons(mismatches: Vec<SnapshotMismatch>) -> Vec<String> {
+        mismatches.iter().map(ToString::to_string).collect()
+    }
+
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn checksum_is_base64_of_the_hex_hash() {
+        let dir = build_dir("checksum", &[("a", b"")]);
+        let mut entry = generate_fileindex(&dir, &[]).unwrap().files.remove(0);
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(entry.hash, EMPTY_SHA256);
+        assert_eq!(
+            entry.checksum_sha256().unwrap(),
+            "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+
+        entry.hash = "not hex".into();
+        assert!(entry.checksum_sha256().is_none());
+        entry.hash = "zz".repeat(32);
+        assert!(entry.checksum_sha256().is_none());
+    }
+
+    #[test]
+    fn index_uses_relative_paths_and_skips_ignored() {
+        let dir = build_dir(
+            "generate",
+            &[
+                ("game.exe", b"MZ"),
+                ("data/a.pak", b"pack"),
+                ("logs/out.log", b"log"),
+            ],
+        );
+        let index = generate_fileindex(&dir, &["logs/**".to_string()]).unwrap();
+
+        let mut paths: Vec<_> = index
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.size))
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [("data/a.pak", 4), ("game.exe", 2)]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ignored_through_a_directory() {
+        let set = build_ignore_set(&["cache".to_string(), "*.tmp".to_string()]).unwrap();
+        assert!(is_ignored(&set, "cache"));
+        assert!(is_ignored(&set, "cache/deep/x.pak"));
+        assert!(is_ignored(&set, "data/x.tmp"));
+        assert!(!is_ignored(&set, "data/cache.pak"));
+        assert!(!is_ignored(&set, "game.exe"));
+    }
+
+    #[test]
+    fn compare_keeps_manifest_and_original_zip() {
+        let dir = build_dir("compare", &[("a", b"new"), ("b", b"same")]);
+        let local = generate_fileindex(&dir, &[]).unwrap();
+
+        let remote_dir = build_dir(
+            "compare-remote",
+            &[
+                ("a", b"old"),
+                ("b", b"same"),
+                ("gone", b""),
+                ("manifest.json", b"{}"),
+                ("build.zip", b""),
+            ],
+        );
+        let remote = generate_fileindex(&remote_dir, &[]).unwrap();
+
+        let changes = compare_fileindex(&local, &remote, &Some("build.zip".into()));
+        let paths = |files: &[FileEntry]| files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert!(changes.new_files.is_empty());
+        assert_eq!(paths(&changes.modified_files), ["a"]);
+        assert_eq!(paths(&changes.deleted_files), ["gone"]);
+
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(remote_dir).unwrap();
+    }
+
+    #[test]
+    fn full_verification_reports_every_difference() {
+        let dir = build_dir("verify", &[("a", b"one"), ("b", b"two"), ("c", b"three")]);
+        let snapshot = generate_fileindex(&dir, &[]).unwrap();
+
+        fs::write(dir.join("a"), b"uno").unwrap();
+        fs::remove_file(dir.join("b")).unwrap();
+        fs::write(dir.join("d"), b"four").unwrap();
+
+        assert_eq!(
+            reasons(verify_fileindex(&dir, &snapshot, &[]).unwrap()),
+            [
+                "a (content differs)",
+                "b (missing in directory)",
+                "d (not in fileindex)"
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sampled_verification_checks_every_size_first() {
+        let dir = build_dir("sample-size", &[("a", b"one"), ("b", b"two")]);
+        let snapshot = generate_fileindex(&dir, &[]).unwrap();
+
+        // Same size, different content: only found when sampled
+        fs::write(dir.join("a"), b"uno").unwrap();
+        assert!(
+            verify_fileindex_sample(&dir, &snapshot, &[], 0)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            reasons(verify_fileindex_sample(&dir, &snapshot, &[], 2).unwrap()),
+            ["a (content differs)"]
+        );
+
+        // A size change is found without hashing anything
+        fs::write(dir.join("b"), b"three").unwrap();
+        fs::write(dir.join("extra"), b"").unwrap();
+        assert_eq!(
+            reasons(verify_fileindex_sample(&dir, &snapshot, &[], 0).unwrap()),
+            ["b (size differs)", "extra (not in fileindex)"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sampled_verification_applies_ignore_patterns() {
+        let dir = build_dir("sample-ignore", &[("a", b"one"), ("cache/x.tmp", b"")]);
+        let snapshot = generate_fileindex(&dir, &["cache/**".to_string()]).unwrap();
+
+        assert!(
+            verify_fileindex_sample(&dir, &snapshot, &["cache/**".to_string()], 1)
+                .unwrap()
+                .is_empty()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sampled_verification_names_non_utf8_files() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = build_dir("sample-utf8", &[("a", b"one")]);
+        let snapshot = generate_fileindex(&dir, &[]).unwrap();
+        fs::write(dir.join(OsStr::from_bytes(b"caf\xe9.txt")), b"").unwrap();
+
+        assert_eq!(
+            reasons(verify_fileindex_sample(&dir, &snapshot, &[], 1).unwrap()),
+            ["caf%E9.txt (name is not valid UTF-8)"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,18 @@
+mod macros;
+
+pub mod archive;
+pub mod changelog;
+pub mod client;
+pub mod config;
+pub mod constants;
+pub mod cover;
+mod credentials;
+pub mod devlog;
+pub mod errors;
+pub mod file_index;
+pub mod network;
+pub mod paths;
+pub mod pusher;
+pub mod schedule;
+pub mod transfer;
+pub mod utils;
@@ -1,30 +1,46 @@
 mod cli;
 mod commands;
-mod constants;
-mod errors;
-mod file_index;
-mod macros;
-mod network;
+mod completion;
+mod logging;
+mod report;
 mod ui;
-mod utils;
 
-use std::{env, fs};
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use cli::{Cli, Commands};
+use report::ErrorReport;
 
-use clap::Parser;
-use cli::Cli;
+use clawdrop::{
+    config::load_config,
+    errors::common::CommonError,
+    network::{NetworkSettings, set_network_settings},
+};
 
-use crate::utils::get_config_path;
+// One runtime for every command
+#[tokio::main]
+async fn main() {
+    // Shell completion requests (COMPLETE=<shell>) are answered here and exit
+    CompleteEnv::with_factory(Cli::command).complete();
 
-fn main() {
-    // We get the API Key from config file if exists
-    let config_path = get_config_path().join(".api_key");
-    if let Ok(api_key) = fs::read_to_string(&config_path) {
-        let api_key = api_key.trim();
-        if !api_key.is_empty() {
-            unsafe { env::set_var("CLAWDROP_API_KEY", api_key) };
-        }
+    let mut cli = Cli::parse();
+    let report = ErrorReport::new(cli.verbose, &cli.output);
+
+    if let Err(e) = logging::init(cli.verbose, cli.log_file.as_deref()) {
+        report.exit("Error opening log file", &CommonError::from(e));
+    }
+
+    // Proxy, CA bundle and timeouts: flags first, then config.json, then the environment
+    let network = std::mem::take(&mut cli.network).into_config();
+    // A broken config.json fails every command but doctor, which reports it with the other checks
+    let config = match load_config() {
+        Ok(config) => config.network,
+        Err(_) if matches!(cli.command, Some(Commands::Doctor)) => Default::default(),
+        Err(e) => report.exit("Error reading config.json", &e),
+    };
+    match NetworkSettings::resolve(network.or(config)) {
+        Ok(settings) => set_network_settings(settings),
+        Err(e) => report.exit("Error", &e),
     }
 
-    let cli = Cli::parse();
-    commands::dispatch(cli);
+    commands::dispatch(cli, report).await;
 }
@@ -1,67 +1,406 @@
-use reqwest::blocking::{Client, ClientBuilder};
+use std::{env, fs, sync::OnceLock, time::Duration};
+
+use aws_sdk_s3::config::SharedHttpClient;
+use aws_smithy_http_client::{
+    Connector,
+    proxy::ProxyConfig,
+    tls::{self, TlsContext, TrustStore, rustls_provider::CryptoMode},
+};
+use chrono::{DateTime, Utc};
+use reqwest::{Certificate, NoProxy, Proxy};
 use serde::{Deserialize, Serialize};
 
-use crate::{constants::{GAMES_LIST_URL, VERIFY_API_KEY_URL}, errors::network::NetworkError};
+use crate::{
+    client::{Game, GameListResponse, RaccreativeClient},
+    config::{NetworkConfig, load_config},
+    constants::{CONNECT_TIMEOUT, GAMES_CACHE_TTL, READ_TIMEOUT},
+    errors::network::NetworkError,
+    utils::get_config_path,
+};
 
-#[derive(Deserialize)]
+// Games list as stored in games_cache.json. Caches written before the timestamp was added count as expired
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct VerifyApiKeyResponse {
-    valid: bool,
+struct GamesCache {
+    #[serde(default)]
+    fetched_at: Option<DateTime<Utc>>,
+    games: Vec<Game>,
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct GameListResponse {
-    pub games: Vec<Game>
+impl GamesCache {
+    fn is_fresh(&self, now: DateTime<Utc>, ttl: u64) -> bool {
+        self.fetched_at
+            .is_some_and(|fetched_at| (now - fetched_at).num_seconds() < ttl as i64)
+    }
 }
 
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Game {
-    pub id: u64,
-    pub title: String,
-    pub url_identifier: Option<String>,
-    pub windows_version: Option<String>,
-    pub linux_version: Option<String>,
-    pub mac_version: Option<String>,
-    pub html_version: Option<String>,
-}
-
-pub fn build_client() -> Result<Client, reqwest::Error> {
-    ClientBuilder::new()
+// Games list and how fresh it is
+pub struct GamesList {
+    pub response: GameListResponse,
+    pub fetched_at: Option<DateTime<Utc>>,
+    // Served from a cache younger than the TTL, without asking the API
+    pub cached: bool,
+    // Why the API could not be used when an expired cache was returned instead
+    pub offline: Option<NetworkError>,
+}
+
+impl GamesList {
+    // Message for the user when the list may be outdated
+    pub fn offline_warning(&self) -> Option<String> {
+        let error = self.offline.as_ref()?;
+        let age = match self.fetched_at {
+            Some(fetched_at) => format!("from {} ago", format_age(Utc::now() - fetched_at)),
+            None => "of unknown age".to_string(),
+        };
+
+        Some(format!(
+            "could not reach Raccreative Games ({}), using the games list cached {}. Versions may be outdated",
+            error, age
+        ))
+    }
+}
+
+// Proxy, extra trusted CA and timeouts used by every HTTP client: the API ones and the S3 connector
+#[derive(Clone)]
+pub struct NetworkSettings {
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub ca_cert_pem: Option<Vec<u8>>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            proxy: None,
+            no_proxy: None,
+            ca_cert_pem: None,
+            connect_timeout: Duration::from_secs(CONNECT_TIMEOUT),
+            read_timeout: Duration::from_secs(READ_TIMEOUT),
+        }
+    }
+}
+
+impl NetworkSettings {
+    // Resolves flags and config.json, falling back to HTTPS_PROXY, ALL_PROXY and NO_PROXY so the S3
+    // client, which ignores the environment, goes through the same proxy. The CA bundle is read and checked here
+    pub fn resolve(config: NetworkConfig) -> Result<Self, NetworkError> {
+        let proxy = config
+            .proxy
+            .or_else(|| env_var(&["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]));
+        let no_proxy = config
+            .no_proxy
+            .or_else(|| env_var(&["NO_PROXY", "no_proxy"]));
+
+        if let Some(proxy) = &proxy {
+            Proxy::all(proxy)
+                .map_err(|e| NetworkError::InvalidSettings(format!("proxy '{}': {}", proxy, e)))?;
+        }
+
+        let ca_cert_pem = match &config.ca_cert {
+            Some(path) => {
+                let pem = fs::read(path).map_err(|e| {
+                    NetworkError::InvalidSettings(format!("CA bundle {}: {}", path.display(), e))
+                })?;
+                match Certificate::from_pem_bundle(&pem) {
+                    Ok(certs) if !certs.is_empty() => Some(pem),
+                    Ok(_) => {
+                        return Err(NetworkError::InvalidSettings(format!(
+                            "CA bundle {} has no PEM certificates",
+                            path.display()
+                        )));
+                    }
+                    Err(e) => {
+                        return Err(NetworkError::InvalidSettings(format!(
+                            "CA bundle {}: {}",
+                            path.display(),
+                            e
+                        )));
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let defaults = NetworkSettings::default();
+        Ok(NetworkSettings {
+            proxy,
+            no_proxy,
+            ca_cert_pem,
+            connect_timeout: config
+                .connect_timeout
+                .map_or(defaults.connect_timeout, Duration::from_secs),
+            read_timeout: config
+                .read_timeout
+                .map_or(defaults.read_timeout, Duration::from_secs),
+        })
+    }
+
+    fn reqwest_proxy(&self) -> Result<Option<Proxy>, reqwest::Error> {
+        let Some(proxy) = &self.proxy else {
+            return Ok(None);
+        };
+
+        let no_proxy = self.no_proxy.as_deref().and_then(NoProxy::from_string);
+        Ok(Some(Proxy::all(proxy)?.no_proxy(no_proxy)))
+    }
+
+    fn certificates(&self) -> Result<Vec<Certificate>, reqwest::Error> {
+        match &self.ca_cert_pem {
+            Some(pem) => Certificate::from_pem_bundle(pem),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+static NETWORK_SETTINGS: OnceLock<NetworkSettings> = OnceLock::new();
+
+// Set once at startup, before any client is built. Without it the defaults are used
+pub fn set_network_settings(settings: NetworkSettings) {
+    let _ = NETWORK_SETTINGS.set(settings);
+}
+
+pub fn network_settings() -> &'static NetworkSettings {
+    NETWORK_SETTINGS.get_or_init(NetworkSettings::default)
+}
+
+// Client of the API and presigned URL transfers
+pub fn build_client() -> Result<reqwest::Client, reqwest::Error> {
+    let settings = network_settings();
+
+    let mut builder = reqwest::Client::builder()
         .use_rustls_tls()
-        .build()
+        .connect_timeout(settings.connect_timeout)
+        .read_timeout(settings.read_timeout);
+
+    if let Some(proxy) = settings.reqwest_proxy()? {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in settings.certificates()? {
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    builder.build()
 }
 
-pub fn verify_api_key(key: &str) -> Result<bool, reqwest::Error> {
-    let client = build_client()?;
+// HTTP client for the S3 SDK with the proxy and CA bundle, timeouts are set on the S3 config
+pub fn build_s3_http_client() -> Result<SharedHttpClient, NetworkError> {
+    let settings = network_settings();
 
-    let response: VerifyApiKeyResponse = client
-        .get(VERIFY_API_KEY_URL)
-        .query(&[("apiKey", &key)])
-        .send()?
-        .json()?;
+    let proxy = match &settings.proxy {
+        Some(url) => {
+            let mut proxy = ProxyConfig::all(url.as_str())
+                .map_err(|e| NetworkError::InvalidSettings(format!("proxy '{}': {}", url, e)))?;
+            if let Ok(parsed) = reqwest::Url::parse(url)
+                && !parsed.username().is_empty()
+            {
+                proxy =
+                    proxy.with_basic_auth(parsed.username(), parsed.password().unwrap_or_default());
+            }
+            match &settings.no_proxy {
+                Some(no_proxy) => proxy.no_proxy(no_proxy),
+                None => proxy,
+            }
+        }
+        None => ProxyConfig::disabled(),
+    };
 
-    Ok(response.valid)
+    let mut trust_store = TrustStore::default();
+    if let Some(pem) = &settings.ca_cert_pem {
+        trust_store = trust_store.with_pem_certificate(pem.clone());
+    }
+    let tls_context = TlsContext::builder()
+        .with_trust_store(trust_store)
+        .build()
+        .map_err(|e| NetworkError::InvalidSettings(e.to_string()))?;
+
+    Ok(
+        aws_smithy_http_client::Builder::new().build_with_connector_fn(
+            move |connector_settings, runtime_components| {
+                let mut builder = Connector::builder()
+                    .tls_provider(tls::Provider::Rustls(CryptoMode::AwsLc))
+                    .tls_context(tls_context.clone())
+                    .proxy_config(proxy.clone());
+                builder.set_connector_settings(connector_settings.cloned());
+                if let Some(components) = runtime_components {
+                    builder.set_sleep_impl(components.sleep_impl());
+                }
+                builder.build()
+            },
+        ),
+    )
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
 }
 
-pub fn get_developed_games_list(api_key: String) -> Result<GameListResponse, NetworkError> {
-    let client = build_client()?;
+// Games list from the cache while it is younger than the TTL (gamesCacheTtl in config.json, in seconds),
+// from the API otherwise or with `refresh`. When the API cannot be reached the cache is used whatever its age
+pub async fn load_games_list(
+    client: &RaccreativeClient,
+    refresh: bool,
+) -> Result<GamesList, NetworkError> {
+    let cache = read_games_cache();
+    let ttl = load_config()
+        .ok()
+        .and_then(|config| config.games_cache_ttl)
+        .unwrap_or(GAMES_CACHE_TTL);
+
+    let fresh = cache
+        .as_ref()
+        .is_some_and(|cache| cache.is_fresh(Utc::now(), ttl));
 
-    let res = match client.get(GAMES_LIST_URL)
-    .header("x-api-key", api_key)
-    .send()
+    if !refresh
+        && fresh
+        && let Some(cache) = cache
     {
-        Ok(r) => {
-            match r.status() {
-                reqwest::StatusCode::FORBIDDEN => return Err(NetworkError::InvalidApiKey),
-                _ => r
-            }
+        return Ok(GamesList {
+            response: GameListResponse { games: cache.games },
+            fetched_at: cache.fetched_at,
+            cached: true,
+            offline: None,
+        });
+    }
+
+    match client.list_games().await {
+        Ok(response) => {
+            write_games_cache(&response);
+            Ok(GamesList {
+                response,
+                fetched_at: Some(Utc::now()),
+                cached: false,
+                offline: None,
+            })
         }
-        Err(e) => return Err(e.into()),
+        Err(e) => fall_back(cache, e),
+    }
+}
+
+// Only connection problems fall back, an invalid key or response must still fail
+fn fall_back(cache: Option<GamesCache>, error: NetworkError) -> Result<GamesList, NetworkError> {
+    match (error, cache) {
+        (error @ NetworkError::Http { .. }, Some(cache)) => Ok(GamesList {
+            response: GameListResponse { games: cache.games },
+            fetched_at: cache.fetched_at,
+            cached: false,
+            offline: Some(error),
+        }),
+        (error, _) => Err(error),
+    }
+}
+
+// Last successful games list, read by shell completion so it never needs the network
+pub fn read_cached_games_list() -> Option<GameListResponse> {
+    read_games_cache().map(|cache| GameListResponse { games: cache.games })
+}
+
+// Versions change with every push and the list depends on the API key, so those drop the cache
+pub fn invalidate_games_cache() {
+    let _ = fs::remove_file(get_config_path().join("games_cache.json"));
+}
+
+fn read_games_cache() -> Option<GamesCache> {
+    let content = fs::read_to_string(get_config_path().join("games_cache.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// The cache is only a convenience, failing to write it must not fail the command
+fn write_games_cache(response: &GameListResponse) {
+    let path = get_config_path().join("games_cache.json");
+    let cache = GamesCache {
+        fetched_at: Some(Utc::now()),
+        games: response.games.clone(),
     };
+    if let Ok(serialized) = serde_json::to_string(&cache) {
+        let _ = fs::create_dir_all(get_config_path());
+        let _ = fs::write(path, serialized);
+    }
+}
+
+fn format_age(age: chrono::TimeDelta) -> String {
+    match age.num_minutes() {
+        minutes if minutes < 1 => "less than a minute".to_string(),
+        minutes if minutes < 60 => format!("{} min", minutes),
+        minutes if minutes < 60 * 24 => format!("{} h", minutes / 60),
+        minutes => format!("{} days", minutes / (60 * 24)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn cache(fetched_at: Option<DateTime<Utc>>) -> GamesCache {
+        serde_json::from_value(serde_json::json!({
+            "fetchedAt": fetched_at,
+            "games": [{ "id": 7, "title": "Moth", "windowsVersion": "1.0.3" }]
+        }))
+        .unwrap()
+    }
 
-    let response: GameListResponse = res.json()?;
+    // Connection error from a port nothing listens on
+    async fn connect_error() -> NetworkError {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
 
-    Ok(response)
-}
\ No newline at end of file
+        let source = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .get(format!("http://{}", addr))
+            .send()
+            .await
+            .unwrap_err();
+        NetworkError::Http {
+            message: "connection refused".to_string(),
+            source,
+        }
+    }
+
+    #[test]
+    fn cache_is_fresh_within_the_ttl() {
+        let now = Utc::now();
+        let fetched = now - TimeDelta::seconds(299);
+
+        assert!(cache(Some(fetched)).is_fresh(now, 300));
+        assert!(!cache(Some(fetched)).is_fresh(now, 299));
+        assert!(!cache(Some(fetched)).is_fresh(now, 0));
+        // Caches from before the timestamp was stored always count as expired
+        assert!(!cache(None).is_fresh(now, u64::MAX / 2));
+    }
+
+    #[tokio::test]
+    async fn connection_errors_fall_back_to_the_cache() {
+        let fetched = Utc::now() - TimeDelta::days(3);
+        let list = fall_back(Some(cache(Some(fetched))), connect_error().await).unwrap();
+
+        assert_eq!(
+            list.response.games[0].version_for("windows").unwrap(),
+            "1.0.3"
+        );
+        assert!(!list.cached);
+        assert_eq!(list.fetched_at, Some(fetched));
+        let warning = list.offline_warning().unwrap();
+        assert!(warning.contains("cached from 3 days ago"), "{}", warning);
+
+        assert!(matches!(
+            fall_back(None, connect_error().await),
+            Err(NetworkError::Http { .. })
+        ));
+    }
+
+    #[test]
+    fn other_errors_do_not_fall_back() {
+        assert!(matches!(
+            fall_back(Some(cache(Some(Utc::now()))), NetworkError::InvalidApiKey),
+            Err(NetworkError::InvalidApiKey)
+        ));
+    }
+}
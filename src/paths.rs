@@ -0,0 +1,225 @@
+use std::{collections::HashMap, fmt, fmt::Write};
+
+use crate::file_index::FileIndex;
+
+// MAX_PATH is 260 on Windows including the install folder, which we do not know. Paths inside
+// the build are kept under this so a typical install location still fits
+const WINDOWS_MAX_RELATIVE: usize = 200;
+const MAC_MAX_PATH: usize = 1024;
+const LINUX_MAX_PATH: usize = 4096;
+// S3 keys are at most 1024 bytes, the game prefix takes part of them
+const HTML_MAX_KEY: usize = 900;
+const MAX_COMPONENT: usize = 255;
+
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// A path of the build that players of the target platform could not install
+#[derive(Debug)]
+pub struct PathProblem {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for PathProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path, self.reason)
+    }
+}
+
+// File name with every byte that is not valid UTF-8 written as %XX, so it can go in a fileindex
+pub fn escape_non_utf8(name: &[u8]) -> String {
+    let mut escaped = String::new();
+    for chunk in name.utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "%{:02X}", byte);
+        }
+    }
+    escaped
+}
+
+// Everything in the index that breaks on the target platform, sorted by path
+pub fn check_paths(index: &FileIndex, os: &str) -> Vec<PathProblem> {
+    let mut problems = Vec::new();
+
+    for entry in &index.files {
+        if let Some(reason) = length_problem(&entry.path, os) {
+            problems.push(PathProblem {
+                path: entry.path.clone(),
+                reason,
+            });
+        }
+        if os == "windows"
+            && let Some(reason) = entry.path.split('/').find_map(windows_name_problem)
+        {
+            problems.push(PathProblem {
+                path: entry.path.clone(),
+                reason,
+            });
+        }
+    }
+
+    // NTFS and APFS ignore case by default, one file would overwrite the other
+    if os == "windows" || os == "mac" {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for entry in &index.files {
+            if let Some(other) = seen.insert(entry.path.to_lowercase(), &entry.path) {
+                problems.push(PathProblem {
+                    path: entry.path.clone(),
+                    reason: format!("same name as {} except for case", other),
+                });
+            }
+        }
+    }
+
+    problems.sort_by(|a, b| a.path.cmp(&b.path));
+    problems
+}
+
+fn length_problem(path: &str, os: &str) -> Option<String> {
+    if let Some(component) = path.split('/').find(|c| c.len() > MAX_COMPONENT) {
+        return Some(format!(
+            "name longer than {} bytes: {}...",
+            MAX_COMPONENT,
+            component.chars().take(20).collect::<String>()
+        ));
+    }
+
+    let (length, limit) = match os {
+        // Windows counts UTF-16 units, the others bytes
+        "windows" => (path.encode_utf16().count(), WINDOWS_MAX_RELATIVE),
+        "mac" => (path.len(), MAC_MAX_PATH),
+        "linux" => (path.len(), LINUX_MAX_PATH),
+        _ => (path.len(), HTML_MAX_KEY),
+    };
+    (length > limit).then(|| format!("path is {} long, {} allows {}", length, os, limit))
+}
+
+fn windows_name_problem(name: &str) -> Option<String> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*') || c.is_control())
+    {
+        return Some(format!(
+            "'{}' is not allowed in Windows names",
+            c.escape_default()
+        ));
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some("Windows drops trailing dots and spaces".to_string());
+    }
+
+    // CON and con.txt alike
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        return Some(format!("{} is a reserved name on Windows", stem));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_index::FileEntry;
+
+    fn index(paths: &[&str]) -> FileIndex {
+        FileIndex {
+            files: paths
+                .iter()
+                .map(|path| FileEntry::new(path.to_string(), 1, String::new(), String::new()))
+                .collect(),
+        }
+    }
+
+    fn problems(paths: &[&str], os: &str) -> Vec<String> {
+        check_paths(&index(paths), os)
+            .into_iter()
+            .map(|problem| problem.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn escape_keeps_valid_utf8() {
+        assert_eq!(escape_non_utf8("música/ñ.txt".as_bytes()), "música/ñ.txt");
+    }
+
+    #[test]
+    fn escape_writes_invalid_bytes_as_hex() {
+        // Latin-1 "mús" and a lone continuation byte
+        assert_eq!(escape_non_utf8(b"m\xfas.txt"), "m%FAs.txt");
+        assert_eq!(escape_non_utf8(b"\x80a\xff"), "%80a%FF");
+    }
+
+    #[test]
+    fn escape_a_truncated_sequence_byte_by_byte() {
+        // The first two bytes of a three byte character, then valid text
+        assert_eq!(escape_non_utf8(b"\xe2\x82b"), "%E2%82b");
+        assert_eq!(escape_non_utf8(b"\xe2\x82\xac"), "€");
+    }
+
+    #[test]
+    fn windows_names() {
+        assert_eq!(
+            problems(
+                &["a:b.txt", "con.txt", "dir./x", "Aux", "console.txt"],
+                "windows"
+            ),
+            [
+                "Aux (Aux is a reserved name on Windows)",
+                "a:b.txt (':' is not allowed in Windows names)",
+                "con.txt (con is a reserved name on Windows)",
+                "dir./x (Windows drops trailing dots and spaces)",
+            ]
+        );
+        assert!(problems(&["a:b.txt", "con.txt"], "linux").is_empty());
+    }
+
+    #[test]
+    fn case_collisions_on_case_insensitive_systems() {
+        let paths = ["Data/a.pak", "data/A.pak"];
+        assert_eq!(
+            problems(&paths, "mac"),
+            ["data/A.pak (same name as Data/a.pak except for case)"]
+        );
+        assert_eq!(problems(&paths, "windows").len(), 1);
+        assert!(problems(&paths, "linux").is_empty());
+    }
+
+    #[test]
+    fn path_lengths_per_os() {
+        let long = format!("{}/{}", "d".repeat(150), "f".repeat(60));
+        assert_eq!(
+            problems(&[&long], "windows"),
+            [format!("{} (path is 211 long, windows allows 200)", long)]
+        );
+        assert!(problems(&[&long], "linux").is_empty());
+
+        let component = "n".repeat(256);
+        assert_eq!(
+            problems(&[&component], "linux"),
+            [format!(
+                "{} (name longer than 255 bytes: {}...)",
+                component,
+                "n".repeat(20)
+            )]
+        );
+    }
+
+    #[test]
+    fn windows_length_counts_utf16_units() {
+        // 141 UTF-16 units but 421 bytes, the same bytes in ASCII are too long
+        let path = format!("{}/{}", "€".repeat(70), "€".repeat(70));
+        assert!(problems(&[&path], "windows").is_empty());
+        assert_eq!(
+            problems(&["a".repeat(path.len()).as_str()], "windows").len(),
+            1
+        );
+    }
+}
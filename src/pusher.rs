@@ -0,0 +1,1164 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sdk_s3::{
+    config::{
+        Credentials, IdentityCache, Region, http::HttpResponse, retry::RetryConfig,
+        timeout::TimeoutConfig,
+    },
+    error::{DisplayErrorContext, ProvideErrorMetadata, SdkError},
+    operation::{RequestId, RequestIdExt},
+    types::{ChecksumAlgorithm, CompletedMultipartUpload, CompletedPart, Delete, ObjectIdentifier},
+};
+use serde::Serialize;
+use tracing::{Instrument, debug, debug_span, info, info_span, warn};
+
+use crate::{
+    archive::{ArchiveEntry, ArchiveKind, ArchiveReader},
+    client::{CompletePushBody, RaccreativeClient},
+    credentials::{CredentialsKind, REFRESH_BEFORE_EXPIRY, SessionCredentials, UploadSession},
+    errors::{
+        common::CommonError,
+        push::{DeleteFailure, PushError, S3Failure},
+    },
+    file_index::{
+        FileChanges, FileEntry, FileIndex, FileSource, UnreadableFile, compare_fileindex,
+        read_fileindex, scan_fileindex, tree_state, verify_fileindex_sample,
+    },
+    network::{build_s3_http_client, network_settings},
+    paths::check_paths,
+    transfer::{
+        RateLimiter, StreamCheck, TransferLimits, TransferSlots, checked_archive_stream,
+        checked_file_stream, file_part_stream,
+    },
+};
+
+// S3 DeleteObjects accepts up to 1000 keys per request
+const DELETE_BATCH_SIZE: usize = 1000;
+// Files from this size go up in parts, PutObject takes at most 5 GiB
+const MULTIPART_THRESHOLD: u64 = 256 * 1024 * 1024;
+const MULTIPART_PART_SIZE: u64 = 64 * 1024 * 1024;
+// S3 limit on the parts of one upload, bigger files get bigger parts
+const MULTIPART_MAX_PARTS: u64 = 10_000;
+const STABLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Any region works to check the connection, uploads use the one of their bucket
+const S3_CHECK_REGION: &str = "us-east-1";
+
+/// Resolved destination of a push.
+pub struct PushParams {
+    pub id: u64,
+    pub os: String,
+    pub exe: String,
+    pub version: String,
+}
+
+/// Where the build comes from and how it is compared with the live one.
+pub struct PushOptions {
+    pub path: PathBuf,
+    pub ignore: Vec<String>,
+    /// Upload every file instead of only new and modified ones.
+    pub force: bool,
+    /// Trusted fileindex snapshot used instead of hashing the build.
+    pub index: Option<PathBuf>,
+    /// Random files re-hashed to check the snapshot.
+    pub index_sample: usize,
+    /// Wait until no file changed for this long before indexing.
+    pub wait_stable: Option<Duration>,
+    /// Leave out files that cannot be read instead of failing.
+    pub skip_unreadable: bool,
+    pub non_utf8: NonUtf8Names,
+}
+
+/// What a push does with file names that are not valid UTF-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonUtf8Names {
+    #[default]
+    Fail,
+    Skip,
+    /// Upload them with every invalid byte written as `%XX`.
+    Escape,
+}
+
+impl FromStr for NonUtf8Names {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fail" => Ok(NonUtf8Names::Fail),
+            "skip" => Ok(NonUtf8Names::Skip),
+            "escape" => Ok(NonUtf8Names::Escape),
+            _ => Err(format!(
+                "invalid value '{}', expected fail, skip or escape",
+                s
+            )),
+        }
+    }
+}
+
+/// What a finished push changed.
+pub struct PushSummary {
+    pub game_id: u64,
+    pub version: String,
+    pub os: String,
+    pub uploaded_files: usize,
+    pub uploaded_bytes: u64,
+    pub deleted_files: usize,
+    /// Obsolete files that stayed on S3, the build is live without them.
+    pub delete_failures: Vec<DeleteFailure>,
+    pub changes: FileChanges,
+    /// Size of the whole build once live.
+    pub total_bytes: u64,
+    /// Download size difference with the previous live build.
+    pub size_delta: i64,
+}
+
+/// Numbered stages of a push, reported in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushStage {
+    Fileindex,
+    Credentials,
+    RemoteFileindex,
+    Compare,
+    Upload,
+    Verify,
+    Delete,
+    Manifest,
+    Complete,
+}
+
+impl PushStage {
+    /// Name used in logs.
+    pub fn name(self) -> &'static str {
+        match self {
+            PushStage::Fileindex => "fileindex",
+            PushStage::Credentials => "credentials",
+            PushStage::RemoteFileindex => "remote_fileindex",
+            PushStage::Compare => "compare",
+            PushStage::Upload => "upload",
+            PushStage::Verify => "verify",
+            PushStage::Delete => "delete",
+            PushStage::Manifest => "manifest",
+            PushStage::Complete => "complete",
+        }
+    }
+}
+
+/// Progress reported by [`Pusher::push`].
+pub enum PushEvent<'a> {
+    Stage(PushStage),
+    /// Files of the build are still changing, indexing starts once they settle.
+    WaitingForStable,
+    /// Files left out of the build with `skip_unreadable`.
+    SkippedUnreadable(&'a [UnreadableFile]),
+    /// Names that are not valid UTF-8, left out or escaped.
+    NonUtf8Names {
+        names: &'a [String],
+        escaped: bool,
+    },
+    Changes(&'a FileChanges),
+    NoChanges,
+    UploadProgress {
+        done_bytes: u64,
+        total_bytes: u64,
+        speed: Option<u64>,
+    },
+    UploadFinished,
+    DeleteProgress {
+        done: usize,
+        total: usize,
+    },
+    DeleteFinished,
+    /// The temporary S3 credentials were about to expire and were renewed, uploads go on.
+    CredentialsRefreshed,
+    Finished,
+}
+
+/// Receives push progress. Closures taking a [`PushEvent`] implement it.
+pub trait PushProgress: Send + Sync {
+    fn report(&self, event: PushEvent<'_>);
+}
+
+impl<F> PushProgress for F
+where
+    F: Fn(PushEvent<'_>) + Send + Sync,
+{
+    fn report(&self, event: PushEvent<'_>) {
+        self(event)
+    }
+}
+
+struct NoProgress;
+
+impl PushProgress for NoProgress {
+    fn report(&self, _event: PushEvent<'_>) {}
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    path: String,
+    version: String,
+}
+
+/// Differential push of a build directory to a game.
+pub struct Pusher {
+    client: RaccreativeClient,
+    limits: TransferLimits,
+    progress: Arc<dyn PushProgress>,
+}
+
+impl Pusher {
+    /// The client must carry an API key with upload permissions for the game.
+    pub fn new(client: RaccreativeClient) -> Self {
+        Self {
+            client,
+            limits: TransferLimits::default(),
+            progress: Arc::new(NoProgress),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: TransferLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: impl PushProgress + 'static) -> Self {
+        self.progress = Arc::new(progress);
+        self
+    }
+
+    pub async fn push(
+        &self,
+        params: &PushParams,
+        options: &PushOptions,
+    ) -> Result<PushSummary, PushError> {
+        let progress = self.progress.as_ref();
+
+        // An export still being written would be indexed half done
+        if let Some(quiet) = options.wait_stable {
+            progress.report(PushEvent::WaitingForStable);
+            wait_until_stable(&options.path, &options.ignore, quiet).await?;
+        }
+
+        // 1. We generate fileindex.json local with ignore patterns if any (or trust the snapshot after a sample check)
+        let fileindex_local = self
+            .stage(PushStage::Fileindex, async {
+                let index = match &options.index {
+                    Some(_) if ArchiveKind::detect(&options.path)?.is_some() => {
+                        return Err(PushError::IndexWithArchive);
+                    }
+                    Some(index_path) => {
+                        let snapshot = read_fileindex(index_path)?;
+                        let mismatches = verify_fileindex_sample(
+                            &options.path,
+                            &snapshot,
+                            &options.ignore,
+                            options.index_sample,
+                        )?;
+                        if !mismatches.is_empty() {
+                            return Err(PushError::IndexMismatch(mismatches));
+                        }
+                        snapshot
+                    }
+                    None => self.scan_build(options)?,
+                };
+
+                // Names the players' file system cannot hold, better now than in a bug report
+                let problems = check_paths(&index, &params.os);
+                if !problems.is_empty() {
+                    return Err(PushError::InvalidPaths(problems));
+                }
+                Ok(index)
+            })
+            .await?;
+        debug!(files = fileindex_local.files.len(), "local fileindex ready");
+        // Shared with the credentials refresh, which sends it again
+        let fileindex_local = Arc::new(fileindex_local);
+
+        // 2. We request the temporal credentials and use them in S3
+        let response = self
+            .stage(PushStage::Credentials, async {
+                self.client
+                    .request_upload(params.id, &params.os, &fileindex_local)
+                    .await
+            })
+            .await?;
+
+        // Bucket, prefix and the remote fileindex never change, a refresh only renews the secrets
+        let upload_bucket = response.upload_credentials.bucket.clone();
+        let upload_prefix = response.upload_credentials.prefix.clone();
+        let delete_bucket = response.delete_credentials.bucket.clone();
+        let delete_prefix = response.delete_credentials.prefix.clone();
+        let upload_region = response.upload_credentials.region.clone();
+        let delete_region = response.delete_credentials.region.clone();
+        let remote_fileindex_url = response.extra_downloads.fileindex.clone();
+        let original_zip_name = response.original_zip_name.clone();
+
+        let session = Arc::new(UploadSession::new(
+            self.client.clone(),
+            params.id,
+            params.os.clone(),
+            fileindex_local.clone(),
+            self.progress.clone(),
+            response,
+        ));
+
+        // We have separate clients for upload and deleting files for better security
+        let s3_client_upload = build_s3_client(
+            SessionCredentials::new(session.clone(), CredentialsKind::Upload),
+            upload_region,
+        )?;
+        let s3_client_delete = build_s3_client(
+            SessionCredentials::new(session.clone(), CredentialsKind::Delete),
+            delete_region,
+        )?;
+
+        // 3. We download remote fileindex.json for local comparison (if no fileindex in remote we create an empty one)
+        let fileindex_remote = self
+            .stage(PushStage::RemoteFileindex, async {
+                Ok(match &remote_fileindex_url {
+                    Some(url) => self.client.download_fileindex(url).await?,
+                    None => FileIndex::default(),
+                })
+            })
+            .await?;
+
+        // 4. Compare local and remote fileindex to see what changed (upload all files if forced)
+        let (changes, files_to_upload) = self
+            .stage(PushStage::Compare, async {
+                let changes =
+                    compare_fileindex(&fileindex_local, &fileindex_remote, &original_zip_name);
+                let files_to_upload = changes.to_upload(options.force, &fileindex_local);
+                debug!(
+                    upload = files_to_upload.len(),
+                    delete = changes.deleted_files.len(),
+                    "compared fileindexes"
+                );
+                Ok((changes, files_to_upload))
+            })
+            .await?;
+
+        let total_bytes: u64 = fileindex_local.files.iter().map(|f| f.size).sum();
+        let remote_bytes: u64 = fileindex_remote.files.iter().map(|f| f.size).sum();
+        let size_delta = total_bytes as i64 - remote_bytes as i64;
+
+        if files_to_upload.is_empty() && changes.deleted_files.is_empty() {
+            info!("no changes to push");
+            progress.report(PushEvent::NoChanges);
+            return Ok(PushSummary {
+                game_id: params.id,
+                version: params.version.clone(),
+                os: params.os.clone(),
+                uploaded_files: 0,
+                uploaded_bytes: 0,
+                deleted_files: 0,
+                delete_failures: Vec::new(),
+                changes,
+                total_bytes,
+                size_delta,
+            });
+        }
+
+        progress.report(PushEvent::Changes(&changes));
+        let mut slots = self.limits.slots();
+
+        // 5. Upload new/modified files (everything if forced, except ignored files)
+        self.stage(
+            PushStage::Upload,
+            upload_files_if_any(
+                progress,
+                &s3_client_upload,
+                &files_to_upload,
+                upload_prefix,
+                &upload_bucket,
+                &options.path,
+                &mut slots,
+            ),
+        )
+        .await?;
+
+        self.stage(PushStage::Verify, async {
+            self.client
+                .verify_upload(params.id, &params.os, &session.upload_id().await)
+                .await
+        })
+        .await?;
+
+        // 6. Delete obsolete remote files in batches (except manifest.json, *.zip). Files that stay
+        // only take space, the new version goes live anyway and they are reported with the summary
+        let delete_failures = self
+            .stage(
+                PushStage::Delete,
+                delete_files_if_any(
+                    progress,
+                    &s3_client_delete,
+                    &changes.deleted_files,
+                    delete_prefix,
+                    &delete_bucket,
+                    &slots,
+                ),
+            )
+            .await?;
+
+        // 7. Upload manifest.json and fileindex.json with presigned urls
+        self.stage(PushStage::Manifest, async {
+            let manifest = Manifest {
+                path: params.exe.clone(),
+                version: params.version.clone(),
+            };
+            let manifest_json =
+                serde_json::to_vec_pretty(&manifest).map_err(CommonError::Encode)?;
+            let fileindex_json =
+                serde_json::to_vec_pretty(&*fileindex_local).map_err(CommonError::Encode)?;
+            let extra_uploads = session.extra_uploads().await;
+
+            self.client
+                .put_presigned_json(&extra_uploads.manifest, "manifest.json", manifest_json)
+                .await?;
+            self.client
+                .put_presigned_json(&extra_uploads.fileindex, "fileindex.json", fileindex_json)
+                .await?;
+            Ok(())
+        })
+        .await?;
+
+        // 8. We update the new version in database and close the upload
+        self.stage(PushStage::Complete, async {
+            let complete_push_body = CompletePushBody {
+                os: params.os.clone(),
+                new_version: params.version.clone(),
+                file_name: original_zip_name
+                    .clone()
+                    .unwrap_or_else(|| format!("{}-{}.zip", params.id, params.os)),
+            };
+
+            self.client
+                .complete_push(params.id, &complete_push_body)
+                .await
+        })
+        .await?;
+
+        progress.report(PushEvent::Finished);
+
+        Ok(PushSummary {
+            game_id: params.id,
+            version: params.version.clone(),
+            os: params.os.clone(),
+            uploaded_files: files_to_upload.len(),
+            uploaded_bytes: files_to_upload.iter().map(|f| f.size).sum(),
+            deleted_files: changes
+                .deleted_files
+                .len()
+                .saturating_sub(delete_failures.len()),
+            delete_failures,
+            changes,
+            total_bytes,
+            size_delta,
+        })
+    }
+
+    // Indexes the build directory, what could not be indexed fails the push unless the options allow it
+    fn scan_build(&self, options: &PushOptions) -> Result<FileIndex, PushError> {
+        let escape = options.non_utf8 == NonUtf8Names::Escape;
+        let scan = scan_fileindex(&options.path, &options.ignore, escape)?;
+
+        if !scan.unreadable.is_empty() {
+            if !options.skip_unreadable {
+                return Err(CommonError::UnreadableFiles(scan.unreadable).into());
+            }
+            warn!(files = scan.unreadable.len(), "skipping unreadable files");
+            self.progress
+                .report(PushEvent::SkippedUnreadable(&scan.unreadable));
+        }
+
+        if !scan.non_utf8.is_empty() {
+            if options.non_utf8 == NonUtf8Names::Fail {
+                return Err(CommonError::NonUtf8Names(scan.non_utf8).into());
+            }
+            warn!(files = scan.non_utf8.len(), escape, "non UTF-8 names");
+            self.progress.report(PushEvent::NonUtf8Names {
+                names: &scan.non_utf8,
+                escaped: escape,
+            });
+        }
+
+        Ok(scan.index)
+    }
+
+    // Reports the stage and runs it inside its own span, so every log line says which stage it came from
+    async fn stage<T>(
+        &self,
+        stage: PushStage,
+        work: impl Future<Output = Result<T, PushError>>,
+    ) -> Result<T, PushError> {
+        self.progress.report(PushEvent::Stage(stage));
+
+        let span = info_span!("stage", name = stage.name());
+        async move {
+            let start = Instant::now();
+            let result = work.await;
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+
+            match &result {
+                Ok(_) => info!(elapsed_ms, "stage finished"),
+                Err(e) => warn!(elapsed_ms, error = %e, "stage failed"),
+            }
+
+            result
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+fn build_s3_client(
+    credentials: impl ProvideCredentials + 'static,
+    region: String,
+) -> Result<aws_sdk_s3::Client, PushError> {
+    let settings = network_settings();
+    let config = aws_sdk_s3::Config::builder()
+        .region(Region::new(region))
+        .credentials_provider(credentials)
+        // Refresh early enough that a request signed now still reaches S3 before expiry
+        .identity_cache(
+            IdentityCache::lazy()
+                .buffer_time(REFRESH_BEFORE_EXPIRY)
+                .load_timeout(settings.connect_timeout + settings.read_timeout)
+                .build(),
+        )
+        .http_client(build_s3_http_client()?)
+        .timeout_config(
+            TimeoutConfig::builder()
+                .connect_timeout(settings.connect_timeout)
+                .read_timeout(settings.read_timeout)
+                .build(),
+        )
+        .behavior_version_latest()
+        .build();
+
+    Ok(aws_sdk_s3::Client::from_conf(config))
+}
+
+/// Sends one request to S3 with placeholder credentials through the configured proxy and CA
+/// bundle. Any answer from S3, even the access error it is bound to be, means DNS, the proxy
+/// and TLS all work for uploads.
+pub async fn check_s3_connection() -> Result<(), PushError> {
+    let credentials = Credentials::new(
+        "clawdrop-check",
+        "clawdrop-check",
+        None,
+        None,
+        "clawdrop-check",
+    );
+    let client = build_s3_client(credentials, S3_CHECK_REGION.to_string())?;
+
+    let result = client
+        .list_buckets()
+        .customize()
+        .config_override(aws_sdk_s3::Config::builder().retry_config(RetryConfig::disabled()))
+        .send()
+        .await;
+
+    match result {
+        Ok(_) | Err(SdkError::ServiceError(_)) => Ok(()),
+        Err(e) => Err(s3_error("connection check".to_string(), e)),
+    }
+}
+
+async fn upload_files_if_any(
+    progress: &dyn PushProgress,
+    s3_client: &aws_sdk_s3::Client,
+    files_to_upload: &[FileEntry],
+    prefix: String,
+    bucket: &str,
+    base_path: &Path,
+    slots: &mut TransferSlots,
+) -> Result<(), PushError> {
+    if files_to_upload.is_empty() {
+        return Ok(());
+    }
+
+    // Archive builds are read in place, without extracting them
+    let archive =
+        ArchiveKind::detect(base_path)?.map(|kind| Arc::new(ArchiveReader::new(base_path, kind)));
+
+    // Size and mtime must still be the indexed ones, or the hash sent to S3 is not this file's
+    let mut prepared_files = Vec::with_capacity(files_to_upload.len());
+    let mut unstable = Vec::new();
+    let mut total_bytes: u64 = 0;
+    for entry in files_to_upload {
+        let source = match (&entry.source, &archive) {
+            (Some(FileSource::Archive(location)), Some(archive)) => {
+                UploadSource::Archive(archive.clone(), *location)
+            }
+            (Some(FileSource::Disk(path)), _) => UploadSource::File(path.clone()),
+            _ => UploadSource::File(base_path.join(&entry.path)),
+        };
+        // Entries of an archive keep the mtime of the archive, their size is not its size
+        let (local_path, check_size) = match &source {
+            UploadSource::File(path) => (path.as_path(), true),
+            UploadSource::Archive(..) => (base_path, false),
+        };
+
+        let metadata = match std::fs::metadata(local_path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                unstable.push(entry.path.clone());
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let modified = metadata.modified().ok();
+        if (check_size && metadata.len() != entry.size)
+            || entry.modified.is_some_and(|m| modified != Some(m))
+        {
+            unstable.push(entry.path.clone());
+            continue;
+        }
+
+        total_bytes += entry.size;
+        prepared_files.push((entry, source, entry.size));
+    }
+
+    if !unstable.is_empty() {
+        warn!(files = unstable.len(), "files changed after indexing");
+        return Err(PushError::UnstableFiles(unstable));
+    }
+
+    progress.report(PushEvent::UploadProgress {
+        done_bytes: 0,
+        total_bytes,
+        speed: None,
+    });
+
+    // A tar.gz can only be read front to back: one upload at a time, in archive order, so each
+    // entry goes on from the previous one. Uploads that start out of order only cost a re-read
+    let sequential = archive
+        .as_ref()
+        .is_some_and(|archive| archive.is_sequential());
+    let mut sequential_slots = slots.sequential();
+    let slots = if sequential {
+        prepared_files.sort_by_key(|(_, source, _)| match source {
+            UploadSource::Archive(_, location) => location.offset(),
+            UploadSource::File(_) => 0,
+        });
+        &mut sequential_slots
+    } else {
+        slots
+    };
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(prepared_files.len());
+    // Dropping the set aborts the uploads still running when one fails
+    let mut tasks = tokio::task::JoinSet::new();
+
+    // Spawn concurrent tasks
+    for (entry, source, size) in prepared_files {
+        let upload = FileUpload {
+            s3_client: s3_client.clone(),
+            bucket: bucket.to_string(),
+            key: format!("{}{}", prefix, entry.path),
+            path: entry.path.clone(),
+            checksum: entry.checksum_sha256(),
+            check: StreamCheck::new(&entry.hash),
+            modified: entry.modified,
+            source,
+            size,
+        };
+        let tx = tx.clone();
+        let sem = slots.semaphore.clone();
+        let limiter = slots.limiter.clone();
+        let span = debug_span!("put_object", key = %upload.key, size);
+
+        tasks.spawn(
+            async move {
+                let _permit = sem.acquire_owned().await.unwrap();
+
+                let result = upload.send(limiter).await.map(|()| size);
+                if result.is_ok() {
+                    debug!("uploaded");
+                }
+
+                // Send progress to main thread, nobody listens anymore once the push failed
+                let _ = tx.send(result).await;
+            }
+            .instrument(span),
+        );
+    }
+
+    drop(tx);
+
+    // Update UI from main thread while results arrive
+    let mut done_bytes: u64 = 0;
+    let start = Instant::now();
+
+    while let Some(result) = rx.recv().await {
+        done_bytes += result?;
+        slots.observe(done_bytes);
+
+        let elapsed = start.elapsed().as_secs_f64();
+        // Speed could be inaccurate... But will be solved when delta-patching is implemented
+        let speed = (elapsed > 0.0).then(|| (done_bytes as f64 / elapsed) as u64);
+
+        progress.report(PushEvent::UploadProgress {
+            done_bytes,
+            total_bytes,
+            speed,
+        });
+    }
+
+    // A task that panicked never sent its result
+    while let Some(joined) = tasks.join_next().await {
+        joined?;
+    }
+
+    progress.report(PushEvent::UploadFinished);
+    Ok(())
+}
+
+struct FileUpload {
+    s3_client: aws_sdk_s3::Client,
+    bucket: String,
+    key: String,
+    path: String,
+    checksum: Option<String>,
+    check: StreamCheck,
+    modified: Option<SystemTime>,
+    source: UploadSource,
+    size: u64,
+}
+
+enum UploadSource {
+    File(PathBuf),
+    Archive(Arc<ArchiveReader>, ArchiveEntry),
+}
+
+impl FileUpload {
+    async fn send(self, limiter: Option<Arc<RateLimiter>>) -> Result<(), PushError> {
+        // Archive entries cannot be read from an offset, they always go in one request
+        if let UploadSource::File(path) = &self.source
+            && self.size >= MULTIPART_THRESHOLD
+        {
+            return self.send_multipart(path, limiter).await;
+        }
+
+        let body = match self.source {
+            UploadSource::File(path) => {
+                checked_file_stream(path, self.size, limiter, self.check.clone())
+            }
+            UploadSource::Archive(archive, entry) => {
+                checked_archive_stream(archive, entry, limiter, self.check.clone())
+            }
+        };
+
+        // The hash from the fileindex lets S3 reject a corrupted upload (BadDigest) without
+        // the SDK reading the file a second time
+        let request = match self.checksum {
+            Some(checksum) => self.s3_client.put_object().checksum_sha256(checksum),
+            None => self
+                .s3_client
+                .put_object()
+                .checksum_algorithm(ChecksumAlgorithm::Sha256),
+        };
+
+        request
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .content_length(self.size as i64)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| {
+                // Written to while it was being read, S3 got no object
+                if self.check.changed() {
+                    PushError::UnstableFiles(vec![self.path.clone()])
+                } else {
+                    s3_error(format!("Failed uploading {}", self.path), e)
+                }
+            })?;
+
+        Ok(())
+    }
+
+    // Parts go up one after another and take the slot of the file. S3 checks the SHA-256 of each
+    // part, the whole file is only checked to still have its indexed size and mtime at the end
+    async fn send_multipart(
+        &self,
+        path: &Path,
+        limiter: Option<Arc<RateLimiter>>,
+    ) -> Result<(), PushError> {
+        let created = self
+            .s3_client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .checksum_algorithm(ChecksumAlgorithm::Sha256)
+            .send()
+            .await
+            .map_err(|e| s3_error(format!("Failed starting upload of {}", self.path), e))?;
+        let upload_id = created.upload_id().unwrap_or_default().to_string();
+
+        let result = self.send_parts(path, &upload_id, limiter).await;
+        if result.is_err() {
+            // The parts would be billed until a lifecycle rule removes them
+            if let Err(e) = self
+                .s3_client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(&self.key)
+                .upload_id(&upload_id)
+                .send()
+                .await
+            {
+                warn!(key = %self.key, error = %DisplayErrorContext(&e), "could not abort multipart upload");
+            }
+        }
+        result
+    }
+
+    async fn send_parts(
+        &self,
+        path: &Path,
+        upload_id: &str,
+        limiter: Option<Arc<RateLimiter>>,
+    ) -> Result<(), PushError> {
+        let mut completed = Vec::new();
+        for (number, offset, size) in multipart_parts(self.size) {
+            let output = self
+                .s3_client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(&self.key)
+                .upload_id(upload_id)
+                .part_number(number)
+                .checksum_algorithm(ChecksumAlgorithm::Sha256)
+                .content_length(size as i64)
+                .body(file_part_stream(
+                    path.to_path_buf(),
+                    offset,
+                    size,
+                    limiter.clone(),
+                ))
+                .send()
+                .await
+                .map_err(|e| {
+                    s3_error(
+                        format!("Failed uploading part {} of {}", number, self.path),
+                        e,
+                    )
+                })?;
+            debug!(part = number, size, "uploaded part");
+
+            completed.push(
+                CompletedPart::builder()
+                    .part_number(number)
+                    .set_e_tag(output.e_tag().map(str::to_string))
+                    .set_checksum_sha256(output.checksum_sha256().map(str::to_string))
+                    .build(),
+            );
+        }
+
+        // Written to while the parts went up, completing would publish a mix of both versions
+        let metadata = std::fs::metadata(path)?;
+        if metadata.len() != self.size
+            || self
+                .modified
+                .is_some_and(|m| metadata.modified().ok() != Some(m))
+        {
+            return Err(PushError::UnstableFiles(vec![self.path.clone()]));
+        }
+
+        self.s3_client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(completed))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| s3_error(format!("Failed completing upload of {}", self.path), e))?;
+
+        Ok(())
+    }
+}
+
+// Number, offset and size of each part of a file of `size` bytes
+fn multipart_parts(size: u64) -> Vec<(i32, u64, u64)> {
+    let part_size = MULTIPART_PART_SIZE.max(size.div_ceil(MULTIPART_MAX_PARTS));
+    (0..size.div_ceil(part_size))
+        .map(|i| {
+            let offset = i * part_size;
+            (i as i32 + 1, offset, part_size.min(size - offset))
+        })
+        .collect()
+}
+
+// Polls sizes and mtimes until nothing changed for `quiet`
+async fn wait_until_stable(
+    path: &Path,
+    ignore: &[String],
+    quiet: Duration,
+) -> Result<(), PushError> {
+    let mut last = tree_state(path, ignore)?;
+    let mut stable_since = Instant::now();
+
+    while stable_since.elapsed() < quiet {
+        tokio::time::sleep(STABLE_POLL_INTERVAL.min(quiet)).await;
+
+        let current = tree_state(path, ignore)?;
+        if current != last {
+            debug!("build still changing");
+            last = current;
+            stable_since = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+// Obsolete files that could not be deleted are returned, only a request that cannot be built fails
+async fn delete_files_if_any(
+    progress: &dyn PushProgress,
+    s3_client: &aws_sdk_s3::Client,
+    files_to_delete: &[FileEntry],
+    prefix: String,
+    bucket: &str,
+    slots: &TransferSlots,
+) -> Result<Vec<DeleteFailure>, PushError> {
+    if files_to_delete.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let total = files_to_delete.len();
+    progress.report(PushEvent::DeleteProgress { done: 0, total });
+
+    let mut tasks = tokio::task::JoinSet::new();
+
+    // Spawn one DeleteObjects request per batch, a failed request only fails its own keys
+    for (paths, objects) in delete_batches(files_to_delete, &prefix)? {
+        let s3_client = s3_client.clone();
+        let bucket = bucket.to_string();
+        let sem = slots.semaphore.clone();
+        let span = debug_span!("delete_objects", keys = paths.len());
+
+        tasks.spawn(
+            async move {
+                let _permit = sem.acquire_owned().await.unwrap();
+
+                let result = s3_client
+                    .delete_objects()
+                    .bucket(&bucket)
+                    .delete(objects)
+                    .send()
+                    .await;
+
+                (paths, result)
+            }
+            .instrument(span),
+        );
+    }
+
+    let mut failures = Vec::new();
+    let mut deleted_done = 0;
+
+    while let Some(joined) = tasks.join_next().await {
+        let (paths, result) = joined?;
+
+        match result {
+            Ok(output) => failures.extend(key_failures(output.errors(), &prefix)),
+            Err(e) => {
+                let message = DisplayErrorContext(&e).to_string();
+                failures.extend(paths.iter().map(|path| DeleteFailure {
+                    path: path.clone(),
+                    message: message.clone(),
+                }));
+            }
+        }
+
+        deleted_done += paths.len();
+        progress.report(PushEvent::DeleteProgress {
+            done: deleted_done,
+            total,
+        });
+    }
+
+    progress.report(PushEvent::DeleteFinished);
+
+    if !failures.is_empty() {
+        warn!(
+            files = failures.len(),
+            "obsolete files could not be deleted"
+        );
+        failures.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    Ok(failures)
+}
+
+// DeleteObjects requests of at most DELETE_BATCH_SIZE keys, with the build paths each one deletes
+fn delete_batches(
+    files_to_delete: &[FileEntry],
+    prefix: &str,
+) -> Result<Vec<(Vec<String>, Delete)>, PushError> {
+    files_to_delete
+        .chunks(DELETE_BATCH_SIZE)
+        .map(|batch| {
+            let paths: Vec<String> = batch.iter().map(|entry| entry.path.clone()).collect();
+            let objects = paths
+                .iter()
+                .map(|path| {
+                    ObjectIdentifier::builder()
+                        .key(format!("{}{}", prefix, path))
+                        .build()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|objects| {
+                    Delete::builder()
+                        .set_objects(Some(objects))
+                        .quiet(true)
+                        .build()
+                })
+                .map_err(|e| {
+                    PushError::S3Error(S3Failure::new(format!(
+                        "Failed preparing delete batch: {}",
+                        e
+                    )))
+                })?;
+
+            Ok((paths, objects))
+        })
+        .collect()
+}
+
+// Keys a DeleteObjects request answered with an error, as build paths
+fn key_failures(errors: &[aws_sdk_s3::types::Error], prefix: &str) -> Vec<DeleteFailure> {
+    errors
+        .iter()
+        .map(|error| {
+            let key = error.key().unwrap_or_default();
+            DeleteFailure {
+                path: key.strip_prefix(prefix).unwrap_or(key).to_string(),
+                message: format!(
+                    "{}: {}",
+                    error.code().unwrap_or("Unknown"),
+                    error.message().unwrap_or("no message")
+                ),
+            }
+        })
+        .collect()
+}
+
+// S3 error code and message for the user, request ids and the whole SDK error for --verbose
+fn s3_error<E>(context: String, e: SdkError<E, HttpResponse>) -> PushError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let message = match (e.code(), e.message()) {
+        (Some(code), Some(message)) => format!("{}: {} ({})", context, code, message),
+        (Some(code), None) => format!("{}: {}", context, code),
+        // No answer from S3, the innermost error says why (timeout, refused, DNS...)
+        _ => {
+            let mut cause: &dyn std::error::Error = &e;
+            while let Some(source) = cause.source() {
+                cause = source;
+            }
+            format!("{}: {}", context, cause)
+        }
+    };
+
+    PushError::S3Error(S3Failure {
+        message,
+        request_id: e.request_id().map(str::to_string),
+        extended_request_id: e.extended_request_id().map(str::to_string),
+        source: Some(Box::new(e)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: String) -> FileEntry {
+        FileEntry::new(path, 1, String::new(), String::new())
+    }
+
+    #[test]
+    fn delete_batches_split_at_the_s3_limit() {
+        let files: Vec<FileEntry> = (0..2500).map(|i| entry(format!("f{}", i))).collect();
+        let batches = delete_batches(&files, "games/1/windows/").unwrap();
+
+        let sizes: Vec<usize> = batches.iter().map(|(paths, _)| paths.len()).collect();
+        assert_eq!(sizes, [1000, 1000, 500]);
+
+        let (paths, objects) = &batches[2];
+        assert_eq!(paths[0], "f2000");
+        assert_eq!(objects.objects()[0].key(), "games/1/windows/f2000");
+        assert_eq!(objects.objects().len(), 500);
+        assert_eq!(objects.quiet(), Some(true));
+    }
+
+    #[test]
+    fn delete_batches_of_nothing() {
+        assert!(delete_batches(&[], "prefix/").unwrap().is_empty());
+    }
+
+    #[test]
+    fn multipart_parts_cover_the_file() {
+        const MIB: u64 = 1024 * 1024;
+
+        let parts = multipart_parts(300 * MIB);
+        assert_eq!(
+            parts,
+            [
+                (1, 0, 64 * MIB),
+                (2, 64 * MIB, 64 * MIB),
+                (3, 128 * MIB, 64 * MIB),
+                (4, 192 * MIB, 64 * MIB),
+                (5, 256 * MIB, 44 * MIB),
+            ]
+        );
+
+        let parts = multipart_parts(256 * MIB);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[3], (4, 192 * MIB, 64 * MIB));
+    }
+
+    #[test]
+    fn multipart_parts_grow_past_the_part_limit() {
+        let size = 1024 * 1024 * 1024 * 1024; // 1 TiB
+        let parts = multipart_parts(size);
+
+        assert!(parts.len() as u64 <= MULTIPART_MAX_PARTS);
+        assert_eq!(parts.iter().map(|(_, _, size)| size).sum::<u64>(), size);
+        assert!(parts.windows(2).all(|w| w[0].1 + w[0].2 == w[1].1));
+    }
+
+    #[test]
+    fn key_failures_are_build_paths() {
+        let errors = [
+            aws_sdk_s3::types::Error::builder()
+                .key("games/1/windows/data/a.pak")
+                .code("AccessDenied")
+                .message("Access Denied")
+                .build(),
+            aws_sdk_s3::types::Error::builder()
+                .key("elsewhere/b.pak")
+                .build(),
+        ];
+
+        let failures = key_failures(&errors, "games/1/windows/");
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].path, "data/a.pak");
+        assert_eq!(failures[0].message, "AccessDenied: Access Denied");
+        assert_eq!(failures[1].path, "elsewhere/b.pak");
+        assert_eq!(failures[1].message, "Unknown: no message");
+    }
+}
@@ -0,0 +1,102 @@
+use std::{collections::BTreeMap, error::Error};
+
+use serde::Serialize;
+
+use clawdrop::{errors::Diagnostic, red};
+
+// How a failed command is reported, from --verbose and --output
+#[derive(Clone, Copy)]
+pub struct ErrorReport {
+    verbose: bool,
+    json: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonReport<'a> {
+    error: JsonError<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonError<'a> {
+    code: &'static str,
+    context: &'a str,
+    message: String,
+    hint: Option<&'static str>,
+    causes: Vec<String>,
+    details: BTreeMap<&'static str, String>,
+}
+
+impl ErrorReport {
+    pub fn new(verbose: u8, output: &str) -> Self {
+        ErrorReport {
+            verbose: verbose > 0,
+            json: output == "json",
+        }
+    }
+
+    // Prints the error with its code and hint and exits with 1. JSON goes to stdout so scripts can parse it
+    pub fn exit(&self, context: &str, error: &dyn Diagnostic) -> ! {
+        let message = error.to_string();
+        let causes = causes(error, &message);
+        let details = error.details();
+
+        if self.json {
+            let report = JsonReport {
+                error: JsonError {
+                    code: error.code(),
+                    context,
+                    message,
+                    hint: error.hint(),
+                    causes,
+                    details: details.into_iter().collect(),
+                },
+            };
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{}", json),
+                Err(_) => eprintln!("{} {}", red!(format!("X {}:", context)), error),
+            }
+            std::process::exit(1);
+        }
+
+        eprintln!(
+            "{} {}",
+            red!(format!("X {} [{}]:", context, error.code())),
+            message
+        );
+        if let Some(hint) = error.hint() {
+            eprintln!("  → {}", hint);
+        }
+
+        if self.verbose {
+            for cause in &causes {
+                eprintln!("  caused by: {}", cause);
+            }
+            for (key, value) in &details {
+                eprintln!("  {}: {}", key, value);
+            }
+        } else if !causes.is_empty() || !details.is_empty() {
+            eprintln!("  run with --verbose for the full cause and request ids");
+        }
+
+        std::process::exit(1);
+    }
+}
+
+// source() chain, leaving out causes the messages above already include
+fn causes(error: &dyn Error, message: &str) -> Vec<String> {
+    let mut causes: Vec<String> = Vec::new();
+    let mut source = error.source();
+
+    while let Some(e) = source {
+        let text = e.to_string();
+        let shown = causes.last().map_or(message, |last| last.as_str());
+        if !text.is_empty() && !shown.contains(&text) {
+            causes.push(text);
+        }
+        source = e.source();
+    }
+
+    causes
+}
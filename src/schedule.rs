@@ -0,0 +1,143 @@
+use std::fs;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::common::CommonError, utils::get_config_path};
+
+const NAIVE_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+// Draft waiting in the local queue until `clawdrop post flush` publishes it
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledPost {
+    pub post_id: u64,
+    pub game_id: u64,
+    pub title: String,
+    pub publish_at: DateTime<Utc>,
+}
+
+// Accepts RFC 3339 (2026-11-01T17:00:00Z, 2026-11-01T18:00:00+01:00) or a date and time
+// followed by an IANA timezone (2026-11-01 17:00 Europe/Madrid). Without zone it is local time
+pub fn parse_publish_at(input: &str) -> Result<DateTime<Utc>, String> {
+    let input = input.trim();
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+
+    let (naive_part, zone) = match input.rsplit_once(' ') {
+        Some((naive, zone)) if zone.parse::<Tz>().is_ok() => (naive, zone.parse::<Tz>().ok()),
+        _ => (input, None),
+    };
+
+    let naive = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(naive_part, format).ok())
+        .ok_or_else(|| {
+            format!(
+                "invalid date '{}', expected RFC 3339 (2026-11-01T17:00:00Z) or '2026-11-01 17:00 Europe/Madrid'",
+                input
+            )
+        })?;
+
+    let datetime = match zone {
+        Some(zone) => zone
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc)),
+    };
+
+    datetime.ok_or_else(|| format!("'{}' does not exist in that timezone (DST gap)", input))
+}
+
+pub fn read_queue() -> Result<Vec<ScheduledPost>, CommonError> {
+    let path = get_config_path().join("post_queue.json");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+pub fn write_queue(queue: &[ScheduledPost]) -> Result<(), CommonError> {
+    fs::create_dir_all(get_config_path())?;
+    let path = get_config_path().join("post_queue.json");
+
+    if queue.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+
+    let json = serde_json::to_string_pretty(queue).map_err(CommonError::Encode)?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(input: &str) -> String {
+        parse_publish_at(input).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn rfc3339_keeps_its_offset() {
+        assert_eq!(utc("2026-11-01T17:00:00Z"), "2026-11-01T17:00:00+00:00");
+        assert_eq!(
+            utc(" 2026-11-01T18:00:00+01:00 "),
+            "2026-11-01T17:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn iana_zone_follows_its_dst_rules() {
+        // Madrid is UTC+1 in winter and UTC+2 in summer
+        assert_eq!(
+            utc("2026-11-01 17:00 Europe/Madrid"),
+            "2026-11-01T16:00:00+00:00"
+        );
+        assert_eq!(
+            utc("2026-07-01T17:00:30 Europe/Madrid"),
+            "2026-07-01T15:00:30+00:00"
+        );
+        assert_eq!(
+            utc("2026-07-01 17:00 America/New_York"),
+            "2026-07-01T21:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn dst_gap_is_rejected_and_overlap_takes_the_earliest() {
+        // Clocks jump from 02:00 to 03:00 on 2026-03-29 in Madrid
+        let error = parse_publish_at("2026-03-29 02:30 Europe/Madrid").unwrap_err();
+        assert!(error.contains("DST gap"), "{}", error);
+
+        // 02:30 happens twice on 2026-10-25, first at UTC+2
+        assert_eq!(
+            utc("2026-10-25 02:30 Europe/Madrid"),
+            "2026-10-25T00:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        assert!(parse_publish_at("tomorrow").is_err());
+        assert!(parse_publish_at("2026-11-01 17:00 Mars/Olympus").is_err());
+        assert!(parse_publish_at("2026-11-01").is_err());
+    }
+}
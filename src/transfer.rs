@@ -0,0 +1,614 @@
+use std::{
+    fmt,
+    future::Future,
+    io::{self, Seek},
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, ready},
+    time::{Duration, Instant},
+};
+
+use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use bytes::Bytes;
+use http_body::{Frame, SizeHint};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    sync::{Semaphore, mpsc},
+    time::Sleep,
+};
+
+use crate::archive::{ArchiveEntry, ArchiveReader};
+
+const DEFAULT_CONCURRENCY: usize = 8;
+const AUTO_START_CONCURRENCY: usize = 4;
+const AUTO_MAX_CONCURRENCY: usize = 32;
+const AUTO_STEP: usize = 2;
+const AUTO_WINDOW: Duration = Duration::from_secs(2);
+const CHUNK_SIZE: usize = 64 * 1024;
+// Chunks an archive reader may get ahead of the upload
+const ARCHIVE_CHANNEL_CHUNKS: usize = 4;
+
+// Number of simultaneous transfers, either fixed or tuned from measured throughput
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "ConcurrencyValue")]
+pub enum Concurrency {
+    Fixed(usize),
+    Auto,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConcurrencyValue {
+    Number(usize),
+    Text(String),
+}
+
+impl Default for Concurrency {
+    fn default() -> Self {
+        Concurrency::Fixed(DEFAULT_CONCURRENCY)
+    }
+}
+
+impl Concurrency {
+    pub fn initial(&self) -> usize {
+        match self {
+            Concurrency::Fixed(n) => *n,
+            Concurrency::Auto => AUTO_START_CONCURRENCY,
+        }
+    }
+}
+
+impl FromStr for Concurrency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Concurrency::Auto);
+        }
+
+        match s.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Concurrency::Fixed(n)),
+            _ => Err(format!(
+                "invalid concurrency '{}', expected a positive number or 'auto'",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<ConcurrencyValue> for Concurrency {
+    type Error = String;
+
+    fn try_from(value: ConcurrencyValue) -> Result<Self, Self::Error> {
+        match value {
+            ConcurrencyValue::Number(n) => n.to_string().parse(),
+            ConcurrencyValue::Text(s) => s.parse(),
+        }
+    }
+}
+
+// Limits shared by every S3 transfer of a push
+#[derive(Clone, Default)]
+pub struct TransferLimits {
+    pub concurrency: Concurrency,
+    pub limiter: Option<Arc<RateLimiter>>,
+}
+
+impl TransferLimits {
+    // Slots for the requests of one push, the uploads and the deletes after them take from the same ones
+    pub fn slots(&self) -> TransferSlots {
+        TransferSlots {
+            semaphore: Arc::new(Semaphore::new(self.concurrency.initial())),
+            tuner: ConcurrencyTuner::new(self.concurrency),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+// Simultaneous S3 requests, grown by the tuner with `auto` while throughput keeps improving
+pub struct TransferSlots {
+    pub semaphore: Arc<Semaphore>,
+    tuner: Option<ConcurrencyTuner>,
+    pub limiter: Option<Arc<RateLimiter>>,
+}
+
+impl TransferSlots {
+    // A single slot that never grows, for transfers that must run one after another
+    pub fn sequential(&self) -> Self {
+        TransferSlots {
+            semaphore: Arc::new(Semaphore::new(1)),
+            tuner: None,
+            limiter: self.limiter.clone(),
+        }
+    }
+
+    pub fn observe(&mut self, done_bytes: u64) {
+        if let Some(tuner) = self.tuner.as_mut() {
+            tuner.observe(done_bytes, &self.semaphore);
+        }
+    }
+}
+
+// Bytes per second, parsed from strings like "500K", "5M" or "1G" (1024 based, like curl)
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ByteRate(pub u64);
+
+impl FromStr for ByteRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid rate '{}', expected a value like 500K, 5M or 1G", s);
+
+        let trimmed = s.trim();
+        let number_end = trimmed
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(number_end);
+
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+        let multiplier = match unit
+            .trim_end_matches(['b', 'B'])
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            "" => 1.0,
+            "K" => 1024.0,
+            "M" => 1024.0 * 1024.0,
+            "G" => 1024.0 * 1024.0 * 1024.0,
+            _ => return Err(invalid()),
+        };
+
+        let rate = (value * multiplier) as u64;
+        if rate == 0 {
+            return Err(invalid());
+        }
+
+        Ok(ByteRate(rate))
+    }
+}
+
+impl TryFrom<String> for ByteRate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for ByteRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} B/s", self.0)
+    }
+}
+
+// Global token bucket shared by every transfer. Callers take the bytes they are about
+// to send and sleep for the debt, so the lock is never held across an await
+pub struct RateLimiter {
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: ByteRate) -> Self {
+        let rate = rate.0 as f64;
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    // Takes `bytes` from the bucket and returns how long the caller must wait before sending them
+    pub fn reserve(&self, bytes: usize) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.rate);
+        bucket.last_refill = now;
+
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+}
+
+// Grows the semaphore while adding more simultaneous uploads keeps improving throughput
+struct ConcurrencyTuner {
+    current: usize,
+    window_start: Instant,
+    window_bytes: u64,
+    best_speed: f64,
+}
+
+impl ConcurrencyTuner {
+    fn new(concurrency: Concurrency) -> Option<Self> {
+        match concurrency {
+            Concurrency::Auto => Some(Self {
+                current: AUTO_START_CONCURRENCY,
+                window_start: Instant::now(),
+                window_bytes: 0,
+                best_speed: 0.0,
+            }),
+            Concurrency::Fixed(_) => None,
+        }
+    }
+
+    fn observe(&mut self, done_bytes: u64, sem: &Semaphore) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < AUTO_WINDOW {
+            return;
+        }
+
+        let speed = (done_bytes - self.window_bytes) as f64 / elapsed.as_secs_f64();
+        if speed > self.best_speed * 1.1 && self.current < AUTO_MAX_CONCURRENCY {
+            sem.add_permits(AUTO_STEP);
+            self.current += AUTO_STEP;
+        }
+
+        self.best_speed = self.best_speed.max(speed);
+        self.window_start = Instant::now();
+        self.window_bytes = done_bytes;
+    }
+}
+
+// Expected SHA-256 of a streamed file. `changed` is set when the bytes read differ from it
+#[derive(Clone)]
+pub struct StreamCheck {
+    pub hash: String,
+    pub changed: Arc<AtomicBool>,
+}
+
+impl StreamCheck {
+    pub fn new(hash: impl Into<String>) -> Self {
+        Self {
+            hash: hash.into(),
+            changed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn changed(&self) -> bool {
+        self.changed.load(Ordering::Relaxed)
+    }
+}
+
+// S3 body that reads a file in chunks, waiting on the shared limiter if any before yielding each
+// one, and hashes it while it is read. A file that no longer matches fails before its last chunk,
+// so S3 never stores it. It is rebuilt from the path on every retry attempt of the SDK
+pub fn checked_file_stream(
+    path: PathBuf,
+    size: u64,
+    limiter: Option<Arc<RateLimiter>>,
+    check: StreamCheck,
+) -> ByteStream {
+    ByteStream::new(SdkBody::retryable(move || {
+        SdkBody::from_body_1_x(FileBody::open(
+            &path,
+            0,
+            size,
+            limiter.clone(),
+            Some(check.clone()),
+        ))
+    }))
+}
+
+// One part of a multipart upload: `size` bytes of the file from `offset`, throttled by the same
+// limiter as whole files. Parts are not hashed here, S3 checks the SHA-256 the SDK sends with each
+pub fn file_part_stream(
+    path: PathBuf,
+    offset: u64,
+    size: u64,
+    limiter: Option<Arc<RateLimiter>>,
+) -> ByteStream {
+    ByteStream::new(SdkBody::retryable(move || {
+        SdkBody::from_body_1_x(FileBody::open(&path, offset, size, limiter.clone(), None))
+    }))
+}
+
+// Like checked_file_stream for a file inside an archive build. A blocking thread reads the entry
+// and sends it in chunks, each retry of the SDK starts a new one
+pub fn checked_archive_stream(
+    archive: Arc<ArchiveReader>,
+    entry: ArchiveEntry,
+    limiter: Option<Arc<RateLimiter>>,
+    check: StreamCheck,
+) -> ByteStream {
+    ByteStream::new(SdkBody::retryable(move || {
+        let (tx, rx) = mpsc::channel(ARCHIVE_CHANNEL_CHUNKS);
+        let archive = archive.clone();
+        tokio::task::spawn_blocking(move || {
+            // A closed channel means the upload was dropped, the reader just stops
+            let read = archive.read_entry(&entry, |data| {
+                tx.blocking_send(Ok(Bytes::copy_from_slice(data))).is_ok()
+            });
+            if let Err(e) = read {
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+
+        SdkBody::from_body_1_x(FileBody::new(
+            Some(BodySource::Channel(rx)),
+            None,
+            entry.size(),
+            limiter.clone(),
+            Some(check.clone()),
+        ))
+    }))
+}
+
+enum BodySource {
+    File(tokio::fs::File),
+    Channel(mpsc::Receiver<io::Result<Bytes>>),
+}
+
+// Body of a file on disk or of an archive entry, throttled and optionally hashed as it is sent
+struct FileBody {
+    source: Option<BodySource>,
+    open_error: Option<io::Error>,
+    remaining: u64,
+    limiter: Option<Arc<RateLimiter>>,
+    check: Option<(StreamCheck, Sha256)>,
+    buffer: Vec<u8>,
+    pending: Option<Bytes>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl FileBody {
+    fn open(
+        path: &PathBuf,
+        offset: u64,
+        size: u64,
+        limiter: Option<Arc<RateLimiter>>,
+        check: Option<StreamCheck>,
+    ) -> Self {
+        let file = std::fs::File::open(path).and_then(|mut f| {
+            f.seek(io::SeekFrom::Start(offset))?;
+            Ok(f)
+        });
+        match file {
+            Ok(f) => Self::new(
+                Some(BodySource::File(tokio::fs::File::from_std(f))),
+                None,
+                size,
+                limiter,
+                check,
+            ),
+            Err(e) => Self::new(None, Some(e), size, limiter, check),
+        }
+    }
+
+    fn new(
+        source: Option<BodySource>,
+        open_error: Option<io::Error>,
+        size: u64,
+        limiter: Option<Arc<RateLimiter>>,
+        check: Option<StreamCheck>,
+    ) -> Self {
+        Self {
+            source,
+            open_error,
+            remaining: size,
+            limiter,
+            check: check.map(|check| (check, Sha256::new())),
+            buffer: vec![0u8; CHUNK_SIZE],
+            pending: None,
+            delay: None,
+        }
+    }
+
+    // Feeds the hasher, and on the last chunk compares the result with the indexed hash
+    fn verify_chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let Some((check, hasher)) = self.check.as_mut() else {
+            return Ok(());
+        };
+        hasher.update(chunk);
+
+        if self.remaining == 0 && format!("{:x}", hasher.clone().finalize()) != check.hash {
+            check.changed.store(true, Ordering::Relaxed);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File content changed after it was indexed",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl http_body::Body for FileBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+
+        if let Some(e) = this.open_error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        if let Some(delay) = this.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            this.delay = None;
+        }
+
+        if let Some(chunk) = this.pending.take() {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        }
+
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        let chunk = match this.source.as_mut() {
+            None => return Poll::Ready(None),
+            Some(BodySource::File(file)) => {
+                let to_read = (this.remaining as usize).min(CHUNK_SIZE);
+                let mut read_buf = ReadBuf::new(&mut this.buffer[..to_read]);
+                ready!(Pin::new(file).poll_read(cx, &mut read_buf))?;
+                Bytes::copy_from_slice(read_buf.filled())
+            }
+            // The reader stops at the entry size, an early end is a short entry
+            Some(BodySource::Channel(rx)) => match ready!(rx.poll_recv(cx)) {
+                Some(chunk) => chunk?,
+                None => Bytes::new(),
+            },
+        };
+
+        let n = chunk.len();
+        if n == 0 {
+            if let Some((check, _)) = &this.check {
+                check.changed.store(true, Ordering::Relaxed);
+            }
+            return Poll::Ready(Some(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "File is shorter than its indexed size",
+            ))));
+        }
+
+        this.remaining -= n as u64;
+        this.verify_chunk(&chunk)?;
+
+        let wait = this
+            .limiter
+            .as_ref()
+            .map_or(Duration::ZERO, |limiter| limiter.reserve(n));
+        if wait.is_zero() {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        }
+
+        let mut delay = Box::pin(tokio::time::sleep(wait));
+        if delay.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        }
+
+        this.pending = Some(chunk);
+        this.delay = Some(delay);
+        Poll::Pending
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0 && self.pending.is_none() && self.open_error.is_none()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let pending = self.pending.as_ref().map_or(0, |chunk| chunk.len() as u64);
+        SizeHint::with_exact(self.remaining + pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_rate_parses_units() {
+        assert_eq!("500K".parse::<ByteRate>().unwrap().0, 500 * 1024);
+        assert_eq!("5M".parse::<ByteRate>().unwrap().0, 5 * 1024 * 1024);
+        assert_eq!("1GB".parse::<ByteRate>().unwrap().0, 1024 * 1024 * 1024);
+        assert_eq!("1.5k".parse::<ByteRate>().unwrap().0, 1536);
+        assert_eq!("100".parse::<ByteRate>().unwrap().0, 100);
+    }
+
+    #[test]
+    fn byte_rate_rejects_invalid() {
+        for rate in ["", "0", "0.0001", "fast", "5T", "M"] {
+            assert!(rate.parse::<ByteRate>().is_err(), "{rate} parsed");
+        }
+    }
+
+    #[test]
+    fn concurrency_parses_numbers_and_auto() {
+        assert!(matches!("AUTO".parse(), Ok(Concurrency::Auto)));
+        assert!(matches!("4".parse(), Ok(Concurrency::Fixed(4))));
+        assert!("0".parse::<Concurrency>().is_err());
+        assert!("-1".parse::<Concurrency>().is_err());
+    }
+
+    #[test]
+    fn rate_limiter_allows_one_second_burst() {
+        let limiter = RateLimiter::new(ByteRate(1000));
+        assert_eq!(limiter.reserve(600), Duration::ZERO);
+        assert_eq!(limiter.reserve(400), Duration::ZERO);
+    }
+
+    #[test]
+    fn rate_limiter_waits_for_the_debt() {
+        let limiter = RateLimiter::new(ByteRate(1000));
+        limiter.reserve(1000);
+
+        // 500 bytes over an empty bucket take half a second at 1000 B/s
+        let wait = limiter.reserve(500);
+        assert!(wait > Duration::from_millis(450) && wait <= Duration::from_millis(500));
+
+        // The debt adds up for the next caller
+        let wait = limiter.reserve(500);
+        assert!(wait > Duration::from_millis(950) && wait <= Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn rate_limiter_refills_up_to_one_second() {
+        let limiter = RateLimiter::new(ByteRate(1000));
+        limiter.reserve(1000);
+        std::thread::sleep(Duration::from_millis(200));
+
+        let wait = limiter.reserve(200);
+        assert!(wait < Duration::from_millis(20), "waited {wait:?}");
+    }
+
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("clawdrop-{}-{}", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn part_stream_reads_its_range() {
+        let path = temp_file("part-range", b"0123456789");
+        let part = file_part_stream(path.clone(), 3, 4, None)
+            .collect()
+            .await
+            .unwrap()
+            .into_bytes();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(&part[..], b"3456");
+    }
+
+    #[tokio::test]
+    async fn part_stream_waits_on_the_limiter() {
+        let path = temp_file("part-limit", &[7u8; 400]);
+        let limiter = Arc::new(RateLimiter::new(ByteRate(1000)));
+        limiter.reserve(1000);
+
+        let start = Instant::now();
+        let part = file_part_stream(path.clone(), 100, 200, Some(limiter))
+            .collect()
+            .await
+            .unwrap()
+            .into_bytes();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(part.len(), 200);
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
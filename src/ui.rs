@@ -6,7 +6,7 @@ use std::sync::{
 use std::thread;
 use std::time::Duration;
 
-use crate::green;
+use clawdrop::{green, utils::format_bytes};
 
 pub struct CliUi {
     width: usize,
@@ -27,23 +27,6 @@ impl CliUi {
         }
     }
 
-    fn format_bytes(bytes: u64) -> String {
-        const KB: f64 = 1024.0;
-        const MB: f64 = KB * 1024.0;
-        const GB: f64 = MB * 1024.0;
-
-        let b = bytes as f64;
-        if b >= GB {
-            format!("{:.1} GB", b / GB)
-        } else if b >= MB {
-            format!("{:.1} MB", b / MB)
-        } else if b >= KB {
-            format!("{:.1} KB", b / KB)
-        } else {
-            format!("{} B", bytes)
-        }
-    }
-
     pub fn set_status(&self, msg: &str) {
         self.finish_status();
         *self.current_msg.lock().unwrap() = msg.to_string();
@@ -108,14 +91,16 @@ impl CliUi {
             io::stdout().flush().unwrap();
         }
 
-        let percent = (total != 0)
-            .then(|| progress as f64 * 100.0 / total as f64)
-            .unwrap_or(0.0);
+        let percent = if total != 0 {
+            progress as f64 * 100.0 / total as f64
+        } else {
+            0.0
+        };
         let filled = ((progress * self.width as u64) / total) as usize;
         let empty = self.width.saturating_sub(filled);
 
         let speed_str = speed
-            .map(|s| format!("  ({}/s)", CliUi::format_bytes(s)))
+            .map(|s| format!("  ({}/s)", format_bytes(s)))
             .unwrap_or_default();
 
         let bar = format!(
@@ -123,8 +108,8 @@ impl CliUi {
             filled_blocks = "█".repeat(filled),
             empty_blocks = "░".repeat(empty),
             percent = percent,
-            done = CliUi::format_bytes(progress as u64),
-            total = CliUi::format_bytes(total as u64),
+            done = format_bytes(progress),
+            total = format_bytes(total),
             speed = speed_str
         );
 
@@ -132,6 +117,12 @@ impl CliUi {
         io::stdout().flush().unwrap();
     }
 
+    // A line above the progress bar, which is drawn again on its next update
+    pub fn print_line(&self, msg: &str) {
+        print!("\r\x1b[2K{}\n", msg);
+        io::stdout().flush().unwrap();
+    }
+
     pub fn finish_progress(&self) {
         self.progress_active.store(false, Ordering::Relaxed);
         println!(" {}", green!("✓"));
@@ -184,7 +175,7 @@ impl CliUi {
 
         SpinnerHandle {
             running,
-            handle,
+            handle: Some(handle),
             msg_ref: Arc::clone(&self.current_msg),
         }
     }
@@ -192,14 +183,13 @@ impl CliUi {
 
 pub struct SpinnerHandle {
     running: Arc<AtomicBool>,
-    handle: thread::JoinHandle<()>,
+    handle: Option<thread::JoinHandle<()>>,
     msg_ref: Arc<Mutex<String>>,
 }
 
 impl SpinnerHandle {
-    pub fn stop(self) {
-        self.running.store(false, Ordering::Relaxed);
-        let _ = self.handle.join();
+    pub fn stop(mut self) {
+        self.halt();
 
         let msg = self.msg_ref.lock().unwrap().clone();
         if !msg.is_empty() {
@@ -207,4 +197,18 @@ impl SpinnerHandle {
             io::stdout().flush().unwrap();
         }
     }
+
+    fn halt(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// A spinner dropped on an error path (without stop) must not keep drawing, e.g. in push --watch
+impl Drop for SpinnerHandle {
+    fn drop(&mut self) {
+        self.halt();
+    }
 }
@@ -1,13 +1,12 @@
 use std::fs;
 use std::{env, path::PathBuf};
 
-use self_update::cargo_crate_version;
-
+use crate::client::{Game, RaccreativeClient};
 use crate::constants::APP_NAME;
 use crate::errors::api_key::ApiKeyError;
 use crate::errors::common::CommonError;
 use crate::errors::set::SetError;
-use crate::network::{Game, get_developed_games_list};
+use crate::network::{GamesList, load_games_list};
 
 pub fn get_config_path() -> PathBuf {
     if cfg!(target_os = "windows") {
@@ -31,10 +30,19 @@ pub fn get_config_path() -> PathBuf {
     }
 }
 
+// The key saved by clawdrop auth, or CLAWDROP_API_KEY (for CI) when there is none.
+// Read on demand instead of copied into the environment, which is unsound once threads run
 pub fn get_api_key() -> Result<String, ApiKeyError> {
-    match std::env::var("CLAWDROP_API_KEY") {
-        Ok(val) if !val.trim().is_empty() => Ok(val),
-        _ => Err(ApiKeyError::MissingEnv),
+    let saved = fs::read_to_string(get_config_path().join(".api_key"))
+        .ok()
+        .map(|key| key.trim().to_string());
+
+    match saved.filter(|key| !key.is_empty()) {
+        Some(key) => Ok(key),
+        None => match std::env::var("CLAWDROP_API_KEY") {
+            Ok(val) if !val.trim().is_empty() => Ok(val),
+            _ => Err(ApiKeyError::MissingEnv),
+        },
     }
 }
 
@@ -51,20 +59,23 @@ pub fn get_target_game() -> Result<Option<Game>, CommonError> {
     Ok(Some(parsed))
 }
 
-pub fn set_target_game(id: String) -> Result<Game, SetError> {
-    let api_key = get_api_key()?;
-
-    let response = get_developed_games_list(api_key)?;
-
+// Saves the game with this id or url slug from the list as target, see `load_games_list`
+pub async fn set_target_game(
+    client: &RaccreativeClient,
+    games: GamesList,
+    id: String,
+) -> Result<Game, SetError> {
     // We find game by id or url slug
-    let game = if let Ok(parsed_id) = id.parse::<u64>() {
-        response.games.into_iter().find(|g| g.id == parsed_id)
-    } else {
-        response
-            .games
-            .into_iter()
-            .find(|g| g.url_identifier.as_deref() == Some(&id))
-    };
+    let mut game = games.response.find(&id);
+
+    // A game created or renamed after the list was cached is only in a fresh one
+    if game.is_none() && games.cached {
+        let games = load_games_list(client, true).await?;
+        if let Some(e) = games.offline {
+            return Err(e.into());
+        }
+        game = games.response.find(&id);
+    }
 
     // If the game is not present then it means that we do not have permission to handle that game (or does not exist)
     let Some(game) = game else {
@@ -82,25 +93,19 @@ pub fn set_target_game(id: String) -> Result<Game, SetError> {
     Ok(game)
 }
 
-pub fn check_for_updates() -> Result<(), Box<dyn std::error::Error>> {
-    let current_version = cargo_crate_version!();
-    let release = self_update::backends::github::Update::configure()
-        .repo_owner("raccreative")
-        .repo_name("clawdrop")
-        .bin_name("clawdrop")
-        .current_version(current_version)
-        .build()?
-        .get_latest_release()?;
-
-    let latest_version = release.version.trim_start_matches('v');
-    let update_available = latest_version != current_version;
-
-    if update_available {
-        println!(
-            "A new version {} is available! (current: {}) Run 'clawdrop upgrade' to update",
-            latest_version, current_version
-        );
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+
+    let b = bytes as f64;
+    if b >= GB {
+        format!("{:.1} GB", b / GB)
+    } else if b >= MB {
+        format!("{:.1} MB", b / MB)
+    } else if b >= KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{} B", bytes)
     }
-
-    Ok(())
 }